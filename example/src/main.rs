@@ -3,12 +3,12 @@ mod biz_model;
 
 use bevy_reflect::{GetField, Reflect};
 use biz_model::*;
-use dapr::appcallback::InvokeResponse;
+use dapr::appcallback::{BindingEventResponse, InvokeResponse};
 use http_body_util::Either;
 use pipe_trait::*;
 use rbatis::*;
 use serde::*;
-use sg_sdk_inner::{daprs::*, log::*, model::*, start::*, traits::*, util::*, *};
+use sg_sdk_inner::{daprs::*, log::*, model::*, server::ServerBuilder, start::*, traits::*, util::*, *};
 use sg_sdk_macro::*;
 use std::collections::*;
 use std::str::FromStr;
@@ -81,17 +81,43 @@ async fn env_prepare(params: &Params) -> HttpResult<(IfRes<EmptyOutPut>, HashMap
         .await
 }
 
+async fn on_app_version_changed(params: &Params) -> HttpResult<(IfRes<EmptyOutPut>, HashMap<String, String>)> {
+    params
+        .pipe(util::params_to_model::<AppVersion, EmptyOutPut, UserWithIdSid>)
+        .await?
+        .pipe(util::validate)?
+        .pipe(util::res)
+        .await
+}
+
+async fn on_app_version_cleanup(params: &Params) -> HttpResult<BindingEventResponse> {
+    info!("app version cleanup triggered, request id: {}", params.request_id);
+    Ok(BindingEventResponse::default())
+}
+
 #[tokio::main]
 async fn main() -> HttpResult<()> {
-    init_log();
+    init_log()?;
 
     ForConfig::insert_uri().await?; // 和 uri! 成对出现
     ForConfig::insert_biz_result().await?; // #[biz_result_handler
     ForConfig::insert_income_param().await?; // income_param!
+    ForConfig::insert_topic().await?; // topic!
+    ForConfig::insert_input_binding().await?; // input_binding!
+    ForConfig::insert_rate_limit().await?; // rate_limit!
+    ForConfig::insert_idempotency().await?; // idempotent!
+    ForConfig::insert_api_model().await?; // api_model!
+    ForConfig::insert_proto_types().await?; // proto_types!
     // ForConfig::set_skip_auth_uri().await?; // skip_auth_uri!
     ForConfig::set_internal_auth_tag().await?; // internal_auth_tag!
 
-    start_http_grpc::<ForConfig>(8080, 8088).await
+    ServerBuilder::new()
+        .http_port(8080)
+        .grpc_port(8088)
+        .native_grpc::<ForConfig>()
+        .api_docs()
+        .serve::<ForConfig>()
+        .await
 }
 
 internal_auth_tag!(ForConfig, "Serverless-Guide");
@@ -100,16 +126,49 @@ internal_auth_tag!(ForConfig, "Serverless-Guide");
 
 uri! {
     ForConfig,
-    (QUERY_BY_APP_ID, GET, "^/example/\\d{19}$", Query, false, true);
-    (INSERT, POST, "^/example$", Insert, false, false);
-    (ENV_PREPARE, GET, "^/example/\\d{19}/env-prepare$", Function, false, false);
+    (QUERY_BY_APP_ID, GET, "/example/{app_id:\\d{19}}", Query, false, true);
+    (INSERT, POST, "/example", Insert, false, false);
+    (ENV_PREPARE, GET, "/example/{app_id:\\d{19}}/env-prepare", Function, false, false);
+}
+
+idempotent! {
+    ForConfig,
+    (INSERT, "statestore", 86400);
+}
+
+api_model! {
+    ForConfig,
+    (QUERY_BY_APP_ID, QueryAppVersions, AppVersion);
+    (INSERT, AppVersion, EmptyOutPut);
+    (ENV_PREPARE, AppVersion, EmptyOutPut);
+}
+
+proto_types! {
+    ForConfig,
+    messages: (AppCodeAndVersion, AppInMapBuilder, UserWithIdSid, IdRes, BulkIdRes, RelId, Role),
+    enums: (RoleCode),
+}
+
+rate_limit! {
+    ForConfig,
+    (ENV_PREPARE, 5.0, 10, 2, JwtSubject);
+}
+
+topic! {
+    ForConfig,
+    (APP_VERSION_CHANGED, "pubsub", "app-version-changed", "/example/events/app-version-changed", on_app_version_changed);
+}
+
+input_binding! {
+    ForConfig,
+    (APP_VERSION_CLEANUP, "app-version-cleanup", on_app_version_cleanup);
 }
 
 income_param! {
     ForConfig,
-    (QUERY_BY_APP_ID, [(app_id, 2, Path, Number, true)]);
+    (QUERY_BY_APP_ID, [(app_id, app_id, Path, Number, true)]);
     (INSERT, [(app_id, app_id, Body, Number, true), (version, version, Body, String, true)]);
-    (ENV_PREPARE, [(app_id, 2, Path, Number, true)]);
+    (ENV_PREPARE, [(app_id, app_id, Path, Number, true)]);
 }
 
 #[biz_result_handler(1002,<CUSTOM_BIZ_RES, 500, 41, "custom biz result message">)] // 依据业务情况， 100241
@@ -0,0 +1,177 @@
+use std::{collections::HashMap, future::Future, pin::Pin};
+
+use dapr::appcallback::{BindingEventRequest, BindingEventResponse, ListInputBindingsResponse};
+use dapr::dapr::dapr::proto::runtime::v1::binding_event_response::BindingEventConcurrency;
+use http_body_util::Either;
+use hyper::{header, Request, Response};
+use serde_json::{json, Value};
+use tonic::Status;
+use tracing::{error, info};
+
+use crate::{
+    body,
+    model::{Action, IfInfo, Params},
+    status,
+    util::{self, find_dapr_binding, utc_timestamp, ResponseError},
+    GrpcResult, HttpResult, INPUT_BINDINGS, ROUTER,
+};
+
+/// binding name, name
+#[derive(PartialEq, Eq, Debug, Hash, Clone, Default)]
+pub struct InputBinding(pub &'static str, pub &'static str);
+
+impl InputBinding {
+    pub fn binding_name(&self) -> &str {
+        &self.0
+    }
+
+    pub fn name(&self) -> &str {
+        &self.1
+    }
+}
+
+pub type BindingHandler = fn(Params) -> Pin<Box<dyn Future<Output = HttpResult<BindingEventResponse>> + Send>>;
+
+pub async fn insert_input_binding(binding: InputBinding, handler: BindingHandler) -> HttpResult<()> {
+    info!("set input binding: {:?}", binding);
+    find_dapr_binding(binding.binding_name())?;
+
+    // 事件路径 /<binding name> 先于路由匹配, 与 uri! 的路由冲突时拒绝
+    if !ROUTER.read().await.registered_methods(&format!("/{}", binding.binding_name())).is_empty() {
+        return Err(Box::new(ResponseError {
+            biz_res: format!("input binding conflicts with uri route: /{}", binding.binding_name()),
+            message: None,
+        }));
+    }
+
+    let mut bindings = INPUT_BINDINGS.write().await;
+    match bindings.insert(binding.binding_name().to_string(), (binding.clone(), handler)) {
+        None => {}
+        Some(_) => {
+            return Err(Box::new(ResponseError {
+                biz_res: format!("input binding is exist: {}", binding.binding_name()),
+                message: None,
+            }));
+        }
+    };
+
+    Ok(())
+}
+
+pub async fn find_input_binding(binding_name: &str) -> Option<(InputBinding, BindingHandler)> {
+    let bindings = INPUT_BINDINGS.read().await;
+    bindings.get(binding_name).map(|(b, h)| (b.clone(), *h))
+}
+
+pub async fn list_input_bindings() -> ListInputBindingsResponse {
+    let bindings = INPUT_BINDINGS.read().await;
+
+    ListInputBindingsResponse {
+        bindings: bindings.keys().cloned().collect(),
+    }
+}
+
+pub async fn on_binding_event_grpc(event: BindingEventRequest) -> GrpcResult<BindingEventResponse> {
+    let Some((binding, handler)) = find_input_binding(&event.name).await else {
+        error!("input binding '{}' has no handler", event.name);
+        return Err(Status::not_found(format!("input binding not found: {}", event.name)));
+    };
+
+    let params = binding_params(&binding, event.metadata, event.data);
+
+    match dispatch(handler, params).await {
+        Ok(res) => Ok(res),
+        Err(err) => Err(status::err_to_status(err).await),
+    }
+}
+
+pub async fn on_binding_event_http(binding: InputBinding, handler: BindingHandler, req: Request<body::ReqBody>) -> Response<Either<body::Body, body::BodySt>> {
+    let mut headers = HashMap::<String, String>::new();
+    for (key, value) in req.headers() {
+        if let Ok(value) = value.to_str() {
+            headers.insert(key.to_string(), value.to_string());
+        }
+    }
+
+    let data = match util::collect_body(req.into_body()).await {
+        Ok(data) => data,
+        Err(err) => {
+            return util::err_resolve(err).await;
+        }
+    };
+
+    let params = binding_params(&binding, headers, data);
+
+    match dispatch(handler, params).await {
+        Ok(res) => Response::builder()
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Either::Left(body::bytes(binding_response_json(res).to_string())))
+            .unwrap(),
+        Err(err) => util::err_resolve(err).await,
+    }
+}
+
+pub fn binding_options_http() -> Response<Either<body::Body, body::BodySt>> {
+    Response::builder().body(Either::Left(body::empty())).unwrap()
+}
+
+fn binding_params(binding: &InputBinding, header: HashMap<String, String>, data: Vec<u8>) -> Params {
+    let mut params: Params = Default::default();
+
+    params.uri = binding.name().to_string();
+    params.header = header.into_iter().map(|(k, v)| (k.to_lowercase(), v)).collect();
+    params.if_info = IfInfo {
+        action: Action::Function,
+        bulk_input: false,
+        bulk_output: false,
+    };
+    params.body = if data.is_empty() { None } else { Some(data) };
+
+    params
+}
+
+async fn dispatch(handler: BindingHandler, params: Params) -> HttpResult<BindingEventResponse> {
+    info!(start_time = %utc_timestamp(), params = ?params, "accept binding event");
+
+    let binding_name = params.uri.clone();
+    let res = handler(params).await;
+
+    match &res {
+        Ok(_) => info!(binding = %binding_name, "binding event handled"),
+        Err(err) => error!(binding = %binding_name, error = ?err, "binding event handled with error"),
+    }
+
+    res
+}
+
+fn binding_response_json(res: BindingEventResponse) -> Value {
+    let data = match serde_json::from_slice::<Value>(&res.data) {
+        Ok(v) => v,
+        Err(_) => Value::String(String::from_utf8_lossy(&res.data).to_string()),
+    };
+
+    let concurrency = match BindingEventConcurrency::from_i32(res.concurrency) {
+        Some(BindingEventConcurrency::Parallel) => "parallel",
+        _ => "sequential",
+    };
+
+    let states: Vec<Value> = res
+        .states
+        .iter()
+        .map(|s| {
+            json!({
+                "key": s.key,
+                "value": serde_json::from_slice::<Value>(&s.value).unwrap_or(Value::String(String::from_utf8_lossy(&s.value).to_string())),
+                "metadata": s.metadata,
+            })
+        })
+        .collect();
+
+    json!({
+        "storeName": res.store_name,
+        "state": states,
+        "to": res.to,
+        "data": data,
+        "concurrency": concurrency,
+    })
+}
@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+
+use prost::Message;
+use prost_types::value::Kind;
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    inner_biz_result::*,
+    model::{IfRes, IfResMessage},
+    traits::ModelTrait,
+    util::{err_boxed_full, err_boxed_full_string, BizResult},
+    HttpResult,
+};
+
+pub const APPLICATION_JSON: &str = "application/json";
+pub const APPLICATION_PROTOBUF: &str = "application/x-protobuf";
+
+/// Res<IfRes<T>> 的 protobuf 形式
+#[derive(Clone, PartialEq, Message)]
+pub struct ResMessage {
+    #[prost(uint32, tag = "1")]
+    pub code: u32,
+    #[prost(string, tag = "2")]
+    pub message: String,
+    #[prost(message, optional, tag = "3")]
+    pub result: Option<IfResMessage>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Codec {
+    #[default]
+    Json,
+    /// 直接为 prost 编码的 message
+    Protobuf,
+    /// dapr gRPC 调用, message 包装在 prost_types::Any 中
+    Grpc,
+}
+
+impl Codec {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Codec::Json => APPLICATION_JSON,
+            Codec::Protobuf => APPLICATION_PROTOBUF,
+            Codec::Grpc => "application/grpc",
+        }
+    }
+
+    /// 请求体的解码方式, 未识别的类型按 JSON 处理
+    pub fn from_content_type(content_type: Option<&str>) -> Codec {
+        let Some(content_type) = content_type else {
+            return Codec::Json;
+        };
+        match essence(content_type).as_str() {
+            "application/grpc" | "application/grpc+proto" => Codec::Grpc,
+            "application/x-protobuf" | "application/protobuf" | "application/vnd.google.protobuf" => Codec::Protobuf,
+            _ => Codec::Json,
+        }
+    }
+
+    /// 依据 Accept 选择响应的编码, 按 q 值从高到低匹配, 没有可用的编码时返回 NOT_ACCEPTABLE
+    pub fn negotiate(accept: Option<&str>) -> HttpResult<Codec> {
+        let Some(accept) = accept.filter(|accept| !accept.trim().is_empty()) else {
+            return Ok(Codec::Json);
+        };
+
+        let mut media_ranges: Vec<(String, f32)> = accept
+            .split(",")
+            .map(|range| {
+                let mut parts = range.split(";");
+                let media_type = essence(parts.next().unwrap_or_default());
+                let q = parts
+                    .filter_map(|p| p.trim().strip_prefix("q="))
+                    .next()
+                    .and_then(|q| q.parse::<f32>().ok())
+                    .unwrap_or(1.0);
+                (media_type, q)
+            })
+            .filter(|(_, q)| *q > 0.0)
+            .collect();
+        media_ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        for (media_type, _) in media_ranges {
+            match media_type.as_str() {
+                "application/json" | "application/*" | "*/*" => return Ok(Codec::Json),
+                "application/x-protobuf" | "application/protobuf" | "application/vnd.google.protobuf" => return Ok(Codec::Protobuf),
+                _ => {}
+            }
+        }
+
+        Err(err_boxed_full(NOT_ACCEPTABLE, accept))
+    }
+
+    pub fn decode<T: DeserializeOwned + Message + Default>(&self, bytes: &[u8]) -> HttpResult<T> {
+        match self {
+            Codec::Json => serde_json::from_slice::<T>(bytes).map_err(|err| err_boxed_full_string(CONVERT_TO_MODEL_ERROR, err.to_string()).into()),
+            Codec::Protobuf => Ok(T::decode(bytes)?),
+            Codec::Grpc => {
+                let any = prost_types::Any::decode(bytes)?;
+                Ok(T::decode(&any.value[..])?)
+            }
+        }
+    }
+
+    pub fn decode_list<T: DeserializeOwned + Message + Default>(&self, bytes: &[u8]) -> HttpResult<Vec<T>> {
+        let list_value = match self {
+            Codec::Json => return serde_json::from_slice::<Vec<T>>(bytes).map_err(|err| err_boxed_full_string(CONVERT_TO_MODEL_ERROR, err.to_string()).into()),
+            Codec::Protobuf => prost_types::ListValue::decode(bytes)?,
+            Codec::Grpc => {
+                let any = prost_types::Any::decode(bytes)?;
+                prost_types::ListValue::decode(&any.value[..])?
+            }
+        };
+
+        let mut list = Vec::<T>::new();
+        for value in list_value.values {
+            if let Some(Kind::StructValue(struct_value)) = value.kind {
+                list.push(T::decode(&struct_value.encode_to_vec()[..])?);
+            }
+        }
+        Ok(list)
+    }
+
+    /// 编码响应的 Res 信封
+    pub fn encode_res<T: ModelTrait + Message + Default + Serialize>(&self, biz_res: BizResult<'static>, result: IfRes<T>) -> HttpResult<Vec<u8>> {
+        match self {
+            Codec::Json => Ok(serde_json::to_vec(&crate::model::Res::<IfRes<T>> {
+                code: biz_res.biz_code(),
+                message: biz_res.message(),
+                result: Some(result),
+            })?),
+            Codec::Protobuf | Codec::Grpc => Ok(ResMessage {
+                code: biz_res.biz_code(),
+                message: biz_res.message(),
+                result: Some(result.to_message()),
+            }
+            .encode_to_vec()),
+        }
+    }
+}
+
+/// Params 中 header 的 key 均为小写
+pub fn content_type(header: &HashMap<String, String>) -> Option<&str> {
+    header.get(hyper::header::CONTENT_TYPE.as_str()).map(|v| v.as_str())
+}
+
+fn essence(media_type: &str) -> String {
+    media_type.split(";").next().unwrap_or_default().trim().to_lowercase()
+}
@@ -1,12 +1,33 @@
 use std::sync::OnceLock;
 
-use dapr::{client::TonicClient, Client};
+use dapr::{client::TonicClient, dapr::dapr::proto::runtime::v1::dapr_client::DaprClient, Client};
+use tokio::sync::OnceCell;
+use tonic::{
+    service::interceptor::InterceptedService,
+    transport::{Channel, Endpoint},
+};
 
-use crate::daprs::dapr_url_grpc;
+use crate::{daprs::dapr_url_grpc, telemetry::OutboundInterceptor, HttpResult};
+
+pub type DaprGrpcClient = DaprClient<InterceptedService<Channel, OutboundInterceptor>>;
 
 static mut DAPR_CLIENT: OnceLock<Client<TonicClient>> = OnceLock::new();
 static DAPR_INITIALIZED: OnceLock<tokio::sync::Mutex<bool>> = OnceLock::new();
+static DAPR_GRPC_CLIENT: OnceCell<DaprGrpcClient> = OnceCell::const_new();
+
+/// 带有 OutboundInterceptor 的原始 gRPC client, 会在请求 metadata 中传递 trace context
+pub async fn get_dapr_grpc_client() -> HttpResult<DaprGrpcClient> {
+    let client = DAPR_GRPC_CLIENT
+        .get_or_try_init(|| async {
+            let channel = Endpoint::from_shared(dapr_url_grpc()?)?.connect().await?;
+            HttpResult::Ok(DaprClient::with_interceptor(channel, OutboundInterceptor))
+        })
+        .await?;
+    Ok(client.clone())
+}
 
+/// dapr sdk 的 Client 无法加入 interceptor, 经由它的调用不会传递 x-request-id 与 traceparent
+#[deprecated(note = "use get_dapr_grpc_client, which propagates x-request-id and trace context")]
 pub async fn get_dapr_client() -> Result<&'static mut Client<TonicClient>, Box<dyn std::error::Error + Sync + Send>> {
     unsafe {
         let client_option = DAPR_CLIENT.get_mut();
@@ -16,7 +37,7 @@ pub async fn get_dapr_client() -> Result<&'static mut Client<TonicClient>, Box<d
         let initializing_mutex = DAPR_INITIALIZED.get_or_init(|| tokio::sync::Mutex::new(false));
         let initialized = initializing_mutex.lock().await;
         if !*initialized {
-            let address = dapr_url_grpc().unwrap();
+            let address = dapr_url_grpc()?;
             let client = Client::<TonicClient>::connect(address).await?;
             let _ = DAPR_CLIENT.set(client);
         }
@@ -436,6 +436,49 @@ impl<I: ModelTrait + prost::Message + Default, O: ModelTrait + prost::Message, C
     }
 }
 
+// 这里全部是`binding_event`相关的方法, 作为 input binding 的响应指令
+impl<I: ModelTrait + prost::Message + Default, O: ModelTrait + prost::Message, C: Clone> ContextWrapper<I, O, C> {
+    pub fn binding_event_concurrency(mut self, concurrency: binding_event_response::BindingEventConcurrency) -> HttpResult<ContextWrapper<I, O, C>> {
+        self.binding_response.concurrency = concurrency as i32;
+
+        Ok(self)
+    }
+
+    pub fn binding_event_store_name(mut self, store_name: &str) -> HttpResult<ContextWrapper<I, O, C>> {
+        find_dapr_state(store_name)?;
+        self.binding_response.store_name = store_name.to_string();
+
+        Ok(self)
+    }
+
+    pub fn binding_event_extend_state(mut self, key: &str, value: Vec<u8>, metadata: HashMap<String, String>) -> HttpResult<ContextWrapper<I, O, C>> {
+        self.binding_response.states.extend(vec![StateItem {
+            key: key.to_string(),
+            value,
+            etag: None,
+            metadata,
+            options: None,
+        }]);
+
+        Ok(self)
+    }
+
+    pub fn binding_event_extend_to(mut self, to: Vec<&str>) -> HttpResult<ContextWrapper<I, O, C>> {
+        for name in &to {
+            find_dapr_binding(name)?;
+        }
+        self.binding_response.to.extend(to.iter().map(|e| e.to_string()));
+
+        Ok(self)
+    }
+
+    pub fn binding_event_data(mut self, data: Vec<u8>) -> HttpResult<ContextWrapper<I, O, C>> {
+        self.binding_response.data = data;
+
+        Ok(self)
+    }
+}
+
 impl<I: ModelTrait + prost::Message + Default, O: ModelTrait + prost::Message, C: Clone> ContextWrapper<I, O, C> {
     pub fn dapr_get_state(self, exec_name: &str, component_name: &str) -> HttpResult<ContextWrapper<I, O, C>> {
         let dapr_req_ins = find_dapr_binding(component_name)?.make_get_state()?;
@@ -1,13 +1,17 @@
 use std::{collections::HashMap, str::FromStr};
 
 use crate::{config::*, inner_biz_result::*, model::*, traits::*, util::*, *};
-use crate::{HttpResult, ENVS};
+use crate::{
+    metrics::{observe_dapr, observe_dapr_http},
+    redact::RedactedValues,
+    HttpResult, ENVS,
+};
 use dapr::dapr::dapr::proto::common::v1::state_options::{StateConcurrency, StateConsistency};
 use dapr::dapr::dapr::proto::{common::v1::InvokeResponse, runtime::v1::*};
 use futures_util::future::join;
 use http_body_util::BodyExt;
 use hyper::{header, Method, StatusCode};
-use tracing::{debug, error, info, trace, warn};
+use tracing::{debug, error, info, instrument, trace, warn};
 
 pub fn check_env_value(value: &str) -> HttpResult<&String> {
     let Some(dapr_host) = ENVS.get(value) else {
@@ -132,6 +136,7 @@ fn append_metadata_to_url(mut url: String, metadata: &HashMap<String, String>) -
     url
 }
 
+#[instrument(skip_all)]
 pub async fn invoke_service_grpc<I: ModelTrait + prost::Message + Default, O: ModelTrait + prost::Message, C: Clone>(
     mut cw: ContextWrapper<I, O, C>,
 ) -> HttpResult<ContextWrapper<I, O, C>> {
@@ -150,10 +155,14 @@ pub async fn invoke_service_grpc<I: ModelTrait + prost::Message + Default, O: Mo
         return Err(err_boxed_full(DAPR_COMPONENT_NOT_EXIST, "dapr_config.invoke_service.message"));
     };
 
-    let response = get_dapr_client()
-        .await?
-        .invoke_service(config.id.to_owned(), message.method.to_owned(), message.data.to_owned())
-        .await;
+    let response = observe_dapr(
+        DaprBuildBlockType::InvokeService,
+        DaprOperationType::InvokeService,
+        &config.id,
+        get_dapr_grpc_client().await?.invoke_service(config.clone()),
+    )
+    .await
+    .map(|r| r.into_inner());
 
     debug!("invoke dapr service '{} {}' response: {:?}", config.id, message.method, response);
 
@@ -169,6 +178,7 @@ pub async fn invoke_service_grpc<I: ModelTrait + prost::Message + Default, O: Mo
     Ok(cw)
 }
 
+#[instrument(skip_all)]
 pub async fn invoke_service_http<I: ModelTrait + prost::Message + Default, O: ModelTrait + prost::Message, C: Clone>(
     mut cw: ContextWrapper<I, O, C>,
 ) -> HttpResult<ContextWrapper<I, O, C>> {
@@ -206,7 +216,13 @@ pub async fn invoke_service_http<I: ModelTrait + prost::Message + Default, O: Mo
 
     debug!("json body is: {:?}", data);
 
-    let mut response = hyper_request(url, http_method, data, Some(message.headers.clone())).await?;
+    let mut response = observe_dapr_http(
+        DaprBuildBlockType::InvokeService,
+        DaprOperationType::InvokeService,
+        &config.id,
+        hyper_request(url, http_method, data, Some(message.headers.clone())),
+    )
+    .await?;
 
     let body_bytes = response.body_mut().collect().await?.to_bytes();
     let body_str = String::from_utf8_lossy(&body_bytes);
@@ -246,6 +262,7 @@ pub async fn invoke_service_http<I: ModelTrait + prost::Message + Default, O: Mo
     Ok(cw)
 }
 
+#[instrument(skip_all)]
 pub async fn get_state_grpc<I: ModelTrait + prost::Message + Default, O: ModelTrait + prost::Message, C: Clone>(
     mut cw: ContextWrapper<I, O, C>,
 ) -> HttpResult<ContextWrapper<I, O, C>> {
@@ -260,10 +277,14 @@ pub async fn get_state_grpc<I: ModelTrait + prost::Message + Default, O: ModelTr
         }
     };
 
-    let response = get_dapr_client()
-        .await?
-        .get_state(config.store_name.clone(), config.key.clone(), Some(config.metadata.clone()))
-        .await;
+    let response = observe_dapr(
+        DaprBuildBlockType::State,
+        DaprOperationType::GetState,
+        &config.store_name,
+        get_dapr_grpc_client().await?.get_state(config.clone()),
+    )
+    .await
+    .map(|r| r.into_inner());
 
     debug!("get dapr state '{}' response: {:?}", config.store_name, response);
 
@@ -279,6 +300,7 @@ pub async fn get_state_grpc<I: ModelTrait + prost::Message + Default, O: ModelTr
     Ok(cw)
 }
 
+#[instrument(skip_all)]
 pub async fn get_state_http<I: ModelTrait + prost::Message + Default, O: ModelTrait + prost::Message, C: Clone>(
     mut cw: ContextWrapper<I, O, C>,
 ) -> HttpResult<ContextWrapper<I, O, C>> {
@@ -317,7 +339,13 @@ pub async fn get_state_http<I: ModelTrait + prost::Message + Default, O: ModelTr
         }
     };
 
-    let mut response = hyper_request(url, Method::GET, None, None).await?;
+    let mut response = observe_dapr_http(
+        DaprBuildBlockType::State,
+        DaprOperationType::GetState,
+        &config.store_name,
+        hyper_request(url, Method::GET, None, None),
+    )
+    .await?;
 
     let body_bytes = response.body_mut().collect().await?.to_bytes();
     debug!("response from dapr body: {}", String::from_utf8_lossy(&body_bytes));
@@ -334,6 +362,7 @@ pub async fn get_state_http<I: ModelTrait + prost::Message + Default, O: ModelTr
     Ok(cw)
 }
 
+#[instrument(skip_all)]
 pub async fn get_bulk_state_http<I: ModelTrait + prost::Message + Default, O: ModelTrait + prost::Message, C: Clone>(
     mut cw: ContextWrapper<I, O, C>,
 ) -> HttpResult<ContextWrapper<I, O, C>> {
@@ -358,7 +387,13 @@ pub async fn get_bulk_state_http<I: ModelTrait + prost::Message + Default, O: Mo
     let url = dapr_get_bulk_state_url_http(config.store_name.as_str())?;
     let url = append_metadata_to_url(url, &config.metadata);
 
-    let mut response = hyper_request(url, Method::POST, Some(data), None).await?;
+    let mut response = observe_dapr_http(
+        DaprBuildBlockType::State,
+        DaprOperationType::GetBulkState,
+        &config.store_name,
+        hyper_request(url, Method::POST, Some(data), None),
+    )
+    .await?;
 
     let body_bytes = response.body_mut().collect().await?.to_bytes();
     debug!("response from dapr body: {}", String::from_utf8_lossy(&body_bytes));
@@ -375,6 +410,7 @@ pub async fn get_bulk_state_http<I: ModelTrait + prost::Message + Default, O: Mo
     Ok(cw)
 }
 
+#[instrument(skip_all)]
 pub async fn query_state_http<I: ModelTrait + prost::Message + Default, O: ModelTrait + prost::Message, C: Clone>(
     mut cw: ContextWrapper<I, O, C>,
 ) -> HttpResult<ContextWrapper<I, O, C>> {
@@ -392,7 +428,13 @@ pub async fn query_state_http<I: ModelTrait + prost::Message + Default, O: Model
     let url = dapr_query_state_url_http(config.store_name.as_str())?;
     let url_with_metadata = append_metadata_to_url(url, &config.metadata);
 
-    let mut response = hyper_request(url_with_metadata, Method::POST, Some(config.query.clone().into_bytes()), None).await?;
+    let mut response = observe_dapr_http(
+        DaprBuildBlockType::State,
+        DaprOperationType::QueryState,
+        &config.store_name,
+        hyper_request(url_with_metadata, Method::POST, Some(config.query.clone().into_bytes()), None),
+    )
+    .await?;
 
     let body_bytes = response.body_mut().collect().await?.to_bytes();
     debug!("response from dapr body: {}", String::from_utf8_lossy(&body_bytes));
@@ -410,6 +452,7 @@ pub async fn query_state_http<I: ModelTrait + prost::Message + Default, O: Model
     Ok(cw)
 }
 
+#[instrument(skip_all)]
 pub async fn save_state_grpc<I: ModelTrait + prost::Message + Default, O: ModelTrait + prost::Message, C: Clone>(
     cw: ContextWrapper<I, O, C>,
 ) -> HttpResult<ContextWrapper<I, O, C>> {
@@ -422,9 +465,13 @@ pub async fn save_state_grpc<I: ModelTrait + prost::Message + Default, O: ModelT
         }
     };
 
-    let data = config.states.iter().map(|item| (item.key.to_owned(), item.value.to_owned()));
-
-    let response = get_dapr_client().await?.save_state(config.store_name.clone(), data).await;
+    let response = observe_dapr(
+        DaprBuildBlockType::State,
+        DaprOperationType::SaveState,
+        &config.store_name,
+        get_dapr_grpc_client().await?.save_state(config.clone()),
+    )
+    .await;
 
     debug!("save dapr state '{}' response: {:?}", config.store_name, response);
 
@@ -435,6 +482,7 @@ pub async fn save_state_grpc<I: ModelTrait + prost::Message + Default, O: ModelT
     Ok(cw)
 }
 
+#[instrument(skip_all)]
 pub async fn save_state_http<I: ModelTrait + prost::Message + Default, O: ModelTrait + prost::Message, C: Clone>(
     cw: ContextWrapper<I, O, C>,
 ) -> HttpResult<ContextWrapper<I, O, C>> {
@@ -451,7 +499,13 @@ pub async fn save_state_http<I: ModelTrait + prost::Message + Default, O: ModelT
 
     let url = dapr_save_state_url_http(config.store_name.as_str())?;
 
-    let response = hyper_request(url, Method::POST, Some(data), None).await?;
+    let response = observe_dapr_http(
+        DaprBuildBlockType::State,
+        DaprOperationType::SaveState,
+        &config.store_name,
+        hyper_request(url, Method::POST, Some(data), None),
+    )
+    .await?;
 
     if &response.status() != &StatusCode::OK {
         return Err(err_boxed(DAPR_REQUEST_FAIL));
@@ -460,6 +514,7 @@ pub async fn save_state_http<I: ModelTrait + prost::Message + Default, O: ModelT
     Ok(cw)
 }
 
+#[instrument(skip_all)]
 pub async fn transaction_state_http<I: ModelTrait + prost::Message + Default, O: ModelTrait + prost::Message, C: Clone>(
     cw: ContextWrapper<I, O, C>,
 ) -> HttpResult<ContextWrapper<I, O, C>> {
@@ -481,7 +536,13 @@ pub async fn transaction_state_http<I: ModelTrait + prost::Message + Default, O:
 
     let url = dapr_transaction_state_url_http(config.store_name.as_str())?;
 
-    let response = hyper_request(url, Method::POST, Some(data), None).await?;
+    let response = observe_dapr_http(
+        DaprBuildBlockType::State,
+        DaprOperationType::TransactionState,
+        &config.store_name,
+        hyper_request(url, Method::POST, Some(data), None),
+    )
+    .await?;
 
     if &response.status() != &StatusCode::OK {
         return Err(err_boxed(DAPR_REQUEST_FAIL));
@@ -490,6 +551,7 @@ pub async fn transaction_state_http<I: ModelTrait + prost::Message + Default, O:
     Ok(cw)
 }
 
+#[instrument(skip_all)]
 pub async fn delete_state_grpc<I: ModelTrait + prost::Message + Default, O: ModelTrait + prost::Message, C: Clone>(
     cw: ContextWrapper<I, O, C>,
 ) -> HttpResult<ContextWrapper<I, O, C>> {
@@ -502,10 +564,13 @@ pub async fn delete_state_grpc<I: ModelTrait + prost::Message + Default, O: Mode
         }
     };
 
-    let response = get_dapr_client()
-        .await?
-        .delete_state(config.store_name.clone(), config.key.clone(), Some(config.metadata.clone()))
-        .await;
+    let response = observe_dapr(
+        DaprBuildBlockType::State,
+        DaprOperationType::DeleteState,
+        &config.store_name,
+        get_dapr_grpc_client().await?.delete_state(config.clone()),
+    )
+    .await;
 
     debug!("delete dapr state '{}' response: {:?}", config.store_name, response);
 
@@ -516,6 +581,7 @@ pub async fn delete_state_grpc<I: ModelTrait + prost::Message + Default, O: Mode
     Ok(cw)
 }
 
+#[instrument(skip_all)]
 pub async fn delete_state_http<I: ModelTrait + prost::Message + Default, O: ModelTrait + prost::Message, C: Clone>(
     cw: ContextWrapper<I, O, C>,
 ) -> HttpResult<ContextWrapper<I, O, C>> {
@@ -576,7 +642,13 @@ pub async fn delete_state_http<I: ModelTrait + prost::Message + Default, O: Mode
         }
     };
 
-    let response = hyper_request(url, Method::POST, None, None).await?;
+    let response = observe_dapr_http(
+        DaprBuildBlockType::State,
+        DaprOperationType::DeleteState,
+        &config.store_name,
+        hyper_request(url, Method::POST, None, None),
+    )
+    .await?;
 
     if &response.status() != &StatusCode::OK {
         return Err(err_boxed(DAPR_REQUEST_FAIL));
@@ -585,6 +657,7 @@ pub async fn delete_state_http<I: ModelTrait + prost::Message + Default, O: Mode
     Ok(cw)
 }
 
+#[instrument(skip_all)]
 pub async fn delete_bulk_state_grpc<I: ModelTrait + prost::Message + Default, O: ModelTrait + prost::Message, C: Clone>(
     cw: ContextWrapper<I, O, C>,
 ) -> HttpResult<ContextWrapper<I, O, C>> {
@@ -597,9 +670,13 @@ pub async fn delete_bulk_state_grpc<I: ModelTrait + prost::Message + Default, O:
         }
     };
 
-    let data = config.states.iter().map(|item| (item.key.to_owned(), item.value.to_owned()));
-
-    let response = get_dapr_client().await?.delete_bulk_state(config.store_name.clone(), data).await;
+    let response = observe_dapr(
+        DaprBuildBlockType::State,
+        DaprOperationType::DeleteBulkState,
+        &config.store_name,
+        get_dapr_grpc_client().await?.delete_bulk_state(config.clone()),
+    )
+    .await;
 
     debug!("delete dapr bulk state '{}' response: {:?}", config.store_name, response);
 
@@ -610,6 +687,7 @@ pub async fn delete_bulk_state_grpc<I: ModelTrait + prost::Message + Default, O:
     Ok(cw)
 }
 
+#[instrument(skip_all)]
 pub async fn delete_bulk_state_http<I: ModelTrait + prost::Message + Default, O: ModelTrait + prost::Message, C: Clone>(
     cw: ContextWrapper<I, O, C>,
 ) -> HttpResult<ContextWrapper<I, O, C>> {
@@ -626,7 +704,13 @@ pub async fn delete_bulk_state_http<I: ModelTrait + prost::Message + Default, O:
 
     let url = dapr_delete_bulk_state_url_http(config.store_name.as_str())?;
 
-    let response = hyper_request(url, Method::DELETE, Some(data), None).await?;
+    let response = observe_dapr_http(
+        DaprBuildBlockType::State,
+        DaprOperationType::DeleteBulkState,
+        &config.store_name,
+        hyper_request(url, Method::DELETE, Some(data), None),
+    )
+    .await?;
 
     if &response.status() != &StatusCode::OK {
         return Err(err_boxed(DAPR_REQUEST_FAIL));
@@ -635,6 +719,7 @@ pub async fn delete_bulk_state_http<I: ModelTrait + prost::Message + Default, O:
     Ok(cw)
 }
 
+#[instrument(skip_all)]
 pub async fn invoke_binding_grpc_sql<I: ModelTrait + prost::Message + Default, O: ModelTrait + prost::Message, C: Clone>(
     mut cw: ContextWrapper<I, O, C>,
 ) -> HttpResult<ContextWrapper<I, O, C>> {
@@ -663,13 +748,32 @@ pub async fn invoke_binding_grpc_sql<I: ModelTrait + prost::Message + Default, O
             page_metadata.insert("sql".to_string(), page_sql.sql.clone());
             page_metadata.insert("params".to_string(), page_sql.params.clone());
 
-            let query = get_dapr_client()
-                .await?
-                .invoke_binding(config.name.clone(), config.data.clone(), query_metadata, config.operation.to_string());
-            let page = get_dapr_client()
-                .await?
-                .invoke_binding(config.name.clone(), config.data.clone(), page_metadata, config.operation.to_string());
+            let mut query_client = get_dapr_grpc_client().await?;
+            let mut page_client = get_dapr_grpc_client().await?;
+            let query = observe_dapr(
+                DaprBuildBlockType::Binding,
+                DaprOperationType::InvokeBindingSql,
+                &config.name,
+                query_client.invoke_binding(InvokeBindingRequest {
+                    name: config.name.clone(),
+                    data: config.data.clone(),
+                    metadata: query_metadata,
+                    operation: config.operation.to_string(),
+                }),
+            );
+            let page = observe_dapr(
+                DaprBuildBlockType::Binding,
+                DaprOperationType::InvokeBindingSql,
+                &config.name,
+                page_client.invoke_binding(InvokeBindingRequest {
+                    name: config.name.clone(),
+                    data: config.data.clone(),
+                    metadata: page_metadata,
+                    operation: config.operation.to_string(),
+                }),
+            );
             let join_res = join(query, page).await;
+            let join_res = (join_res.0.map(|r| r.into_inner()), join_res.1.map(|r| r.into_inner()));
 
             debug!("invoke dapr binding sql query response: {:?}", join_res.0);
             debug!("invoke dapr binding sql page query response: {:?}", join_res.1);
@@ -716,10 +820,19 @@ pub async fn invoke_binding_grpc_sql<I: ModelTrait + prost::Message + Default, O
             metadata.insert("sql".to_string(), sql.sql.clone());
             metadata.insert("params".to_string(), sql.params.clone());
 
-            let response = get_dapr_client()
-                .await?
-                .invoke_binding(config.name.clone(), config.data.clone(), metadata, config.operation.to_string())
-                .await;
+            let response = observe_dapr(
+                DaprBuildBlockType::Binding,
+                DaprOperationType::InvokeBindingSql,
+                &config.name,
+                get_dapr_grpc_client().await?.invoke_binding(InvokeBindingRequest {
+                    name: config.name.clone(),
+                    data: config.data.clone(),
+                    metadata,
+                    operation: config.operation.to_string(),
+                }),
+            )
+            .await
+            .map(|r| r.into_inner());
 
             debug!("invoke dapr binding sql response: {:?}", response);
 
@@ -748,6 +861,7 @@ pub async fn invoke_binding_grpc_sql<I: ModelTrait + prost::Message + Default, O
     }
 }
 
+#[instrument(skip_all)]
 pub async fn invoke_binding_grpc<I: ModelTrait + prost::Message + Default, O: ModelTrait + prost::Message, C: Clone>(
     mut cw: ContextWrapper<I, O, C>,
 ) -> HttpResult<ContextWrapper<I, O, C>> {
@@ -762,10 +876,14 @@ pub async fn invoke_binding_grpc<I: ModelTrait + prost::Message + Default, O: Mo
         }
     };
 
-    let response = get_dapr_client()
-        .await?
-        .invoke_binding(config.name.clone(), config.data.clone(), config.metadata.clone(), config.operation.clone())
-        .await;
+    let response = observe_dapr(
+        DaprBuildBlockType::Binding,
+        DaprOperationType::InvokeBinding,
+        &config.name,
+        get_dapr_grpc_client().await?.invoke_binding(config.clone()),
+    )
+    .await
+    .map(|r| r.into_inner());
 
     debug!("invoke dapr binding response: {:?}", response);
 
@@ -781,6 +899,7 @@ pub async fn invoke_binding_grpc<I: ModelTrait + prost::Message + Default, O: Mo
     Ok(cw)
 }
 
+#[instrument(skip_all)]
 pub async fn invoke_binding_http_sql<I: ModelTrait + prost::Message + Default, O: ModelTrait + prost::Message, C: Clone>(
     mut cw: ContextWrapper<I, O, C>,
 ) -> HttpResult<ContextWrapper<I, O, C>> {
@@ -826,8 +945,18 @@ pub async fn invoke_binding_http_sql<I: ModelTrait + prost::Message + Default, O
             .to_string()
             .into_bytes();
 
-            let query = hyper_request(url.clone(), Method::POST, Some(query_data), None);
-            let page = hyper_request(url.clone(), Method::POST, Some(page_data), None);
+            let query = observe_dapr_http(
+                DaprBuildBlockType::Binding,
+                DaprOperationType::InvokeBindingSql,
+                &config.name,
+                hyper_request(url.clone(), Method::POST, Some(query_data), None),
+            );
+            let page = observe_dapr_http(
+                DaprBuildBlockType::Binding,
+                DaprOperationType::InvokeBindingSql,
+                &config.name,
+                hyper_request(url.clone(), Method::POST, Some(page_data), None),
+            );
 
             let join_res = join(query, page).await;
 
@@ -889,7 +1018,13 @@ pub async fn invoke_binding_http_sql<I: ModelTrait + prost::Message + Default, O
             .to_string()
             .into_bytes();
 
-            let response = hyper_request(url, Method::POST, Some(data), None).await;
+            let response = observe_dapr_http(
+                DaprBuildBlockType::Binding,
+                DaprOperationType::InvokeBindingSql,
+                &config.name,
+                hyper_request(url, Method::POST, Some(data), None),
+            )
+            .await;
 
             debug!("invoke dapr binding sql response: {:?}", response);
 
@@ -922,6 +1057,7 @@ pub async fn invoke_binding_http_sql<I: ModelTrait + prost::Message + Default, O
     }
 }
 
+#[instrument(skip_all)]
 pub async fn invoke_binding_http<I: ModelTrait + prost::Message + Default, O: ModelTrait + prost::Message, C: Clone>(
     mut cw: ContextWrapper<I, O, C>,
 ) -> HttpResult<ContextWrapper<I, O, C>> {
@@ -946,7 +1082,13 @@ pub async fn invoke_binding_http<I: ModelTrait + prost::Message + Default, O: Mo
 
     let url = dapr_invoke_binding_url_http(config.name.as_str())?;
 
-    let mut response = hyper_request(url, Method::POST, Some(data), None).await?;
+    let mut response = observe_dapr_http(
+        DaprBuildBlockType::Binding,
+        DaprOperationType::InvokeBinding,
+        &config.name,
+        hyper_request(url, Method::POST, Some(data), None),
+    )
+    .await?;
 
     let body_bytes = response.body_mut().collect().await?.to_bytes();
     debug!("response from dapr body: {}", String::from_utf8_lossy(&body_bytes));
@@ -964,6 +1106,7 @@ pub async fn invoke_binding_http<I: ModelTrait + prost::Message + Default, O: Mo
     Ok(cw)
 }
 
+#[instrument(skip_all)]
 pub async fn publish_event_grpc<I: ModelTrait + prost::Message + Default, O: ModelTrait + prost::Message, C: Clone>(
     cw: ContextWrapper<I, O, C>,
 ) -> HttpResult<ContextWrapper<I, O, C>> {
@@ -976,16 +1119,13 @@ pub async fn publish_event_grpc<I: ModelTrait + prost::Message + Default, O: Mod
         }
     };
 
-    let response = get_dapr_client()
-        .await?
-        .publish_event(
-            config.pubsub_name.clone(),
-            config.topic.clone(),
-            config.data_content_type.clone(),
-            config.data.clone(),
-            Some(config.metadata.clone()),
-        )
-        .await;
+    let response = observe_dapr(
+        DaprBuildBlockType::Pubsub,
+        DaprOperationType::PublishEvent,
+        &config.pubsub_name,
+        get_dapr_grpc_client().await?.publish_event(config.clone()),
+    )
+    .await;
 
     debug!("publish dapr event '{}.{}' response: {:?}", config.pubsub_name, config.topic, response);
 
@@ -996,6 +1136,7 @@ pub async fn publish_event_grpc<I: ModelTrait + prost::Message + Default, O: Mod
     Ok(cw)
 }
 
+#[instrument(skip_all)]
 pub async fn publish_event_http<I: ModelTrait + prost::Message + Default, O: ModelTrait + prost::Message, C: Clone>(
     cw: ContextWrapper<I, O, C>,
 ) -> HttpResult<ContextWrapper<I, O, C>> {
@@ -1011,7 +1152,13 @@ pub async fn publish_event_http<I: ModelTrait + prost::Message + Default, O: Mod
     let url = dapr_publish_event_url_http(&config.pubsub_name, &config.topic)?;
     let url_with_metadata = append_metadata_to_url(url, &config.metadata);
 
-    let response = hyper_request(url_with_metadata, Method::POST, Some(config.data.clone()), None).await?;
+    let response = observe_dapr_http(
+        DaprBuildBlockType::Pubsub,
+        DaprOperationType::PublishEvent,
+        &config.pubsub_name,
+        hyper_request(url_with_metadata, Method::POST, Some(config.data.clone()), None),
+    )
+    .await?;
 
     if &response.status() != &StatusCode::OK {
         return Err(err_boxed(DAPR_REQUEST_FAIL));
@@ -1020,6 +1167,7 @@ pub async fn publish_event_http<I: ModelTrait + prost::Message + Default, O: Mod
     Ok(cw)
 }
 
+#[instrument(skip_all)]
 pub async fn publish_bulk_event_http<I: ModelTrait + prost::Message + Default, O: ModelTrait + prost::Message, C: Clone>(
     mut cw: ContextWrapper<I, O, C>,
 ) -> HttpResult<ContextWrapper<I, O, C>> {
@@ -1039,7 +1187,13 @@ pub async fn publish_bulk_event_http<I: ModelTrait + prost::Message + Default, O
     let url = dapr_publish_bulk_url_http(&config.pubsub_name, &config.topic)?;
     let url_with_metadata = append_metadata_to_url(url, &config.metadata);
 
-    let mut response = hyper_request(url_with_metadata, Method::POST, Some(data), None).await?;
+    let mut response = observe_dapr_http(
+        DaprBuildBlockType::Pubsub,
+        DaprOperationType::PublishBulkEvent,
+        &config.pubsub_name,
+        hyper_request(url_with_metadata, Method::POST, Some(data), None),
+    )
+    .await?;
 
     if response.status() == StatusCode::INTERNAL_SERVER_ERROR {
         let body_bytes = response.body_mut().collect().await?.to_bytes();
@@ -1060,6 +1214,7 @@ pub async fn publish_bulk_event_http<I: ModelTrait + prost::Message + Default, O
     Ok(cw)
 }
 
+#[instrument(skip_all)]
 pub async fn get_secret_grpc<I: ModelTrait + prost::Message + Default, O: ModelTrait + prost::Message, C: Clone>(
     mut cw: ContextWrapper<I, O, C>,
 ) -> HttpResult<ContextWrapper<I, O, C>> {
@@ -1074,9 +1229,16 @@ pub async fn get_secret_grpc<I: ModelTrait + prost::Message + Default, O: ModelT
         }
     };
 
-    let response = get_dapr_client().await?.get_secret(config.store_name.clone(), config.key.clone()).await;
+    let response = observe_dapr(
+        DaprBuildBlockType::Secret,
+        DaprOperationType::GetSecret,
+        &config.store_name,
+        get_dapr_grpc_client().await?.get_secret(config.clone()),
+    )
+    .await
+    .map(|r| r.into_inner());
 
-    debug!("get dapr secret response: {:?}", response);
+    debug!("get dapr secret response: {:?}", response.as_ref().map(|res| RedactedValues(&res.data)));
 
     if let Err(err) = response {
         return Err(err_boxed_full_string(DAPR_REQUEST_FAIL, err.to_string()));
@@ -1090,6 +1252,7 @@ pub async fn get_secret_grpc<I: ModelTrait + prost::Message + Default, O: ModelT
     Ok(cw)
 }
 
+#[instrument(skip_all)]
 pub async fn get_secret_http<I: ModelTrait + prost::Message + Default, O: ModelTrait + prost::Message, C: Clone>(
     mut cw: ContextWrapper<I, O, C>,
 ) -> HttpResult<ContextWrapper<I, O, C>> {
@@ -1108,10 +1271,16 @@ pub async fn get_secret_http<I: ModelTrait + prost::Message + Default, O: ModelT
     url.push_str(&config.key);
     let url_with_metadata = append_metadata_to_url(url, &config.metadata);
 
-    let mut response = hyper_request(url_with_metadata, Method::GET, None, None).await?;
+    let mut response = observe_dapr_http(
+        DaprBuildBlockType::Secret,
+        DaprOperationType::GetSecret,
+        &config.store_name,
+        hyper_request(url_with_metadata, Method::GET, None, None),
+    )
+    .await?;
 
     let body_bytes = response.body_mut().collect().await?.to_bytes();
-    debug!("response from dapr body: {}", String::from_utf8_lossy(&body_bytes));
+    debug!("response from dapr secret store status: {}", response.status());
 
     if &response.status() != &StatusCode::OK {
         return Err(err_boxed(DAPR_REQUEST_FAIL));
@@ -1125,6 +1294,7 @@ pub async fn get_secret_http<I: ModelTrait + prost::Message + Default, O: ModelT
     Ok(cw)
 }
 
+#[instrument(skip_all)]
 pub async fn get_bulk_secret_http<I: ModelTrait + prost::Message + Default, O: ModelTrait + prost::Message, C: Clone>(
     mut cw: ContextWrapper<I, O, C>,
 ) -> HttpResult<ContextWrapper<I, O, C>> {
@@ -1141,10 +1311,16 @@ pub async fn get_bulk_secret_http<I: ModelTrait + prost::Message + Default, O: M
 
     let url = dapr_get_bulk_secret_url_http(&config.store_name)?;
 
-    let mut response = hyper_request(url, Method::GET, None, None).await?;
+    let mut response = observe_dapr_http(
+        DaprBuildBlockType::Secret,
+        DaprOperationType::GetBulkSecret,
+        &config.store_name,
+        hyper_request(url, Method::GET, None, None),
+    )
+    .await?;
 
     let body_bytes = response.body_mut().collect().await?.to_bytes();
-    debug!("response from dapr body: {}", String::from_utf8_lossy(&body_bytes));
+    debug!("response from dapr secret store status: {}", response.status());
 
     if &response.status() != &StatusCode::OK {
         return Err(err_boxed(DAPR_REQUEST_FAIL));
@@ -1158,6 +1334,7 @@ pub async fn get_bulk_secret_http<I: ModelTrait + prost::Message + Default, O: M
     Ok(cw)
 }
 
+#[instrument(skip_all)]
 pub async fn get_configuration_http<I: ModelTrait + prost::Message + Default, O: ModelTrait + prost::Message, C: Clone>(
     mut cw: ContextWrapper<I, O, C>,
 ) -> HttpResult<ContextWrapper<I, O, C>> {
@@ -1181,7 +1358,13 @@ pub async fn get_configuration_http<I: ModelTrait + prost::Message + Default, O:
         url.push_str(&keys_string);
     }
 
-    let mut response = hyper_request(url, Method::GET, None, None).await?;
+    let mut response = observe_dapr_http(
+        DaprBuildBlockType::Conf,
+        DaprOperationType::GetConfiguration,
+        &config.store_name,
+        hyper_request(url, Method::GET, None, None),
+    )
+    .await?;
 
     let body_bytes = response.body_mut().collect().await?.to_bytes();
     debug!("response from dapr body: {}", String::from_utf8_lossy(&body_bytes));
@@ -0,0 +1,113 @@
+use std::{collections::HashSet, env, future::Future, pin::Pin, time::Duration};
+
+use dapr::dapr::dapr::proto::runtime::v1::{GetMetadataRequest, GetMetadataResponse};
+use http_body_util::Either;
+use hyper::{header, Response, StatusCode};
+use serde_json::{json, Map, Value};
+use tokio::time::timeout;
+use tracing::{info, warn};
+
+use crate::{body, config::get_dapr_grpc_client, shutdown, HttpResult, DAPR_CONFIG, READINESS_CHECKS};
+
+pub const HEALTHZ_PATH: &str = "/healthz";
+pub const READYZ_PATH: &str = "/readyz";
+
+const SIDECAR_PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+pub type ReadinessCheck = fn() -> Pin<Box<dyn Future<Output = HttpResult<()>> + Send>>;
+
+pub async fn insert_readiness_check(name: &str, check: ReadinessCheck) -> HttpResult<()> {
+    info!("set readiness check: {}", name);
+    let mut checks = READINESS_CHECKS.write().await;
+    if checks.iter().any(|(n, _)| n == name) {
+        return Err(format!("readiness check is exist: {}", name).into());
+    }
+    checks.push((name.to_string(), check));
+    Ok(())
+}
+
+pub fn liveness() -> Response<Either<body::Body, body::BodySt>> {
+    json_resp(StatusCode::OK, json!({ "status": "UP" }))
+}
+
+pub async fn readiness() -> Response<Either<body::Body, body::BodySt>> {
+    if shutdown::is_shutting_down() {
+        return json_resp(StatusCode::SERVICE_UNAVAILABLE, json!({ "status": "DOWN", "reason": "shutting down" }));
+    }
+
+    let checks = READINESS_CHECKS.read().await.clone();
+
+    let mut ready = true;
+    let mut details = Map::new();
+    for (name, check) in checks {
+        match check().await {
+            Ok(_) => {
+                details.insert(name, Value::String("UP".to_string()));
+            }
+            Err(err) => {
+                warn!("readiness check '{}' failed: {}", name, err);
+                ready = false;
+                details.insert(name, Value::String(format!("DOWN: {}", err)));
+            }
+        }
+    }
+
+    if ready {
+        json_resp(StatusCode::OK, json!({ "status": "UP", "checks": details }))
+    } else {
+        json_resp(StatusCode::SERVICE_UNAVAILABLE, json!({ "status": "DOWN", "checks": details }))
+    }
+}
+
+/// 每次都通过 pipeline 使用的 gRPC client 调用 sidecar 的 GetMetadata, 缓存的 client 连接成功不代表 sidecar 仍可用
+async fn dapr_metadata() -> HttpResult<GetMetadataResponse> {
+    let mut client = get_dapr_grpc_client().await?;
+    let response = timeout(SIDECAR_PROBE_TIMEOUT, client.get_metadata(GetMetadataRequest::default()))
+        .await
+        .map_err(|_| format!("dapr sidecar probe timeout after {:?}", SIDECAR_PROBE_TIMEOUT))??;
+    Ok(response.into_inner())
+}
+
+pub fn check_dapr_sidecar() -> Pin<Box<dyn Future<Output = HttpResult<()>> + Send>> {
+    Box::pin(async move {
+        dapr_metadata().await?;
+        Ok(())
+    })
+}
+
+/// DAPR_CONFIG 中声明的组件需全部在 sidecar 的 metadata 中
+pub fn check_dapr_components() -> Pin<Box<dyn Future<Output = HttpResult<()>> + Send>> {
+    Box::pin(async move {
+        if env::var("DAPR_CONFIG").is_err() {
+            return Ok(());
+        }
+
+        let metadata = dapr_metadata().await?;
+        let registered: HashSet<&str> = metadata.registered_components.iter().map(|c| c.name.as_str()).collect();
+
+        let missing: Vec<&str> = DAPR_CONFIG
+            .binding
+            .iter()
+            .chain(DAPR_CONFIG.state.iter())
+            .chain(DAPR_CONFIG.pubsub.iter())
+            .chain(DAPR_CONFIG.secret.iter())
+            .chain(DAPR_CONFIG.conf.iter())
+            .map(|c| c.name.as_str())
+            .filter(|name| !registered.contains(name))
+            .collect();
+
+        if !missing.is_empty() {
+            return Err(format!("dapr components not loaded: {}", missing.join(",")).into());
+        }
+
+        Ok(())
+    })
+}
+
+fn json_resp(status: StatusCode, json: Value) -> Response<Either<body::Body, body::BodySt>> {
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Either::Left(body::bytes(json.to_string())))
+        .unwrap()
+}
@@ -0,0 +1,401 @@
+use std::collections::HashMap;
+
+use base64::{engine::general_purpose, Engine};
+use dapr::{
+    appcallback::InvokeResponse,
+    dapr::dapr::proto::{
+        common::v1::{state_options::StateConcurrency, Etag, StateItem, StateOptions},
+        runtime::v1::{DeleteStateRequest, GetStateRequest, SaveStateRequest},
+    },
+};
+use http_body_util::{BodyExt, Either};
+use hyper::{header, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tonic::{metadata::MetadataValue, Code, Status};
+use tracing::{debug, info, warn};
+
+use crate::{
+    body,
+    config::get_dapr_grpc_client,
+    inner_biz_result::*,
+    metrics::BizCode,
+    model::{Action, AuthHeader, Params},
+    rate_limit::jwt_subject,
+    util::{err_boxed_full, ResponseError, URI},
+    GrpcResult, HttpResult, BIZ_RESULT_MAP, IDEMPOTENT_URIS,
+};
+
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+/// 重放的响应带有该 header
+pub const IDEMPOTENT_REPLAYED_HEADER: &str = "idempotent-replayed";
+/// in progress 标记的默认租期
+pub const DEFAULT_LEASE_SECONDS: u64 = 60;
+
+#[derive(Debug, Clone)]
+pub struct Idempotency {
+    pub uri: URI,
+    pub store_name: &'static str,
+    /// 完成后的记录保存的时长
+    pub ttl_seconds: u64,
+    /// in progress 标记的租期, 实例异常退出未释放标记时, 超过租期后可重试; 需长于 handler 的最长执行时间, 否则处理中的请求会被重复执行
+    pub lease_seconds: u64,
+}
+
+impl Idempotency {
+    pub fn new(uri: URI, store_name: &'static str, ttl_seconds: u64) -> Self {
+        Idempotency {
+            uri,
+            store_name,
+            ttl_seconds,
+            lease_seconds: DEFAULT_LEASE_SECONDS,
+        }
+    }
+
+    pub fn lease_seconds(mut self, lease_seconds: u64) -> Self {
+        self.lease_seconds = lease_seconds;
+        self
+    }
+}
+
+/// 同一个 key 在不同协议下的响应格式不同, 分开记录
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+    Http,
+    Grpc,
+    NativeGrpc,
+}
+
+impl Protocol {
+    fn as_str(&self) -> &str {
+        match self {
+            Protocol::Http => "http",
+            Protocol::Grpc => "grpc",
+            Protocol::NativeGrpc => "native-grpc",
+        }
+    }
+}
+
+/// 保存在 state store 中的记录, completed 为 false 时表示请求处理中
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IdempotencyRecord {
+    pub owner: String,
+    /// 请求 body 的 SHA-256, 同一个 key 不能用于不同的请求
+    pub request_hash: String,
+    pub completed: bool,
+    pub status_code: u16,
+    pub biz_code: u32,
+    pub content_type: String,
+    /// base64 编码的响应 body
+    pub body: String,
+}
+
+impl IdempotencyRecord {
+    fn body_bytes(&self) -> Vec<u8> {
+        general_purpose::STANDARD.decode(&self.body).unwrap_or_default()
+    }
+}
+
+pub enum Idempotent {
+    /// 未配置幂等或请求未带 Idempotency-Key
+    Skip,
+    Replay(IdempotencyRecord),
+    Proceed(IdempotencyGuard),
+}
+
+/// 持有 in progress 标记, handler 执行后记录响应或释放标记
+pub struct IdempotencyGuard {
+    store_name: String,
+    key: String,
+    ttl_seconds: u64,
+    etag: String,
+    owner: String,
+    request_hash: String,
+}
+
+impl IdempotencyGuard {
+    async fn complete(self, status_code: u16, biz_code: u32, content_type: String, body: &[u8]) {
+        let record = IdempotencyRecord {
+            owner: self.owner.clone(),
+            request_hash: self.request_hash.clone(),
+            completed: true,
+            status_code,
+            biz_code,
+            content_type,
+            body: general_purpose::STANDARD.encode(body),
+        };
+        if let Err(err) = save_record(&self.store_name, &self.key, &record, Some(self.etag.clone()), self.ttl_seconds).await {
+            warn!("save idempotency record '{}' error: {}", self.key, err);
+            self.release().await;
+        }
+    }
+
+    /// 处理失败时删除标记, 以便重试
+    async fn release(self) {
+        let res = match get_dapr_grpc_client().await {
+            Ok(mut client) => client
+                .delete_state(DeleteStateRequest {
+                    store_name: self.store_name.clone(),
+                    key: self.key.clone(),
+                    etag: Some(Etag { value: self.etag.clone() }),
+                    ..Default::default()
+                })
+                .await
+                .map(|_| ())
+                .map_err(|status| status.message().to_string()),
+            Err(err) => Err(err.to_string()),
+        };
+        if let Err(err) = res {
+            warn!("delete idempotency marker '{}' error: {}", self.key, err);
+        }
+    }
+}
+
+pub async fn insert_idempotency(idempotency: Idempotency) -> HttpResult<()> {
+    info!("set idempotency: {:?}", idempotency);
+    match idempotency.uri.action() {
+        Action::Insert | Action::Update | Action::TX => {}
+        action => {
+            return Err(Box::new(ResponseError {
+                biz_res: format!("idempotency not supported for action {:?}: {}", action, idempotency.uri.name()),
+                message: None,
+            }));
+        }
+    }
+
+    let mut idempotent_uris = IDEMPOTENT_URIS.write().await;
+    idempotent_uris.insert(idempotency.uri.name().to_string(), idempotency);
+    Ok(())
+}
+
+/// 在 handler 之前执行: 已完成的 key 重放响应, 处理中的 key 返回 IDEMPOTENCY_KEY_IN_PROGRESS, 新的 key 写入 in progress 标记
+pub async fn begin(params: &Params, protocol: Protocol) -> HttpResult<Idempotent> {
+    let idempotency = match IDEMPOTENT_URIS.read().await.get(&params.uri) {
+        Some(idempotency) => idempotency.clone(),
+        None => return Ok(Idempotent::Skip),
+    };
+
+    let Some(idempotency_key) = params
+        .header
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(IDEMPOTENCY_KEY_HEADER))
+        .map(|(_, v)| v.trim())
+    else {
+        return Ok(Idempotent::Skip);
+    };
+    if idempotency_key.is_empty() {
+        return Ok(Idempotent::Skip);
+    }
+
+    let store_name = idempotency.store_name.to_string();
+    let key = format!("idempotency||{}||{}||{}||{}", protocol.as_str(), params.uri, caller(params), idempotency_key);
+    let request_hash = hex::encode(Sha256::digest(params.body.as_deref().unwrap_or_default()));
+
+    if let Some((record, _)) = get_record(&store_name, &key).await? {
+        if record.request_hash != request_hash {
+            return Err(err_boxed_full(IDEMPOTENCY_KEY_REUSED, idempotency_key));
+        }
+        if !record.completed {
+            return Err(err_boxed_full(IDEMPOTENCY_KEY_IN_PROGRESS, idempotency_key));
+        }
+        debug!("idempotency key '{}' replayed", idempotency_key);
+        return Ok(Idempotent::Replay(record));
+    }
+
+    let marker = IdempotencyRecord {
+        owner: params.request_id.clone(),
+        request_hash: request_hash.clone(),
+        ..Default::default()
+    };
+    // first-write 且不带 etag 时只在 key 不存在时写入, 并发的首次请求只有一个写入成功, 其余为 etag 冲突
+    match save_record(&store_name, &key, &marker, None, idempotency.lease_seconds).await {
+        Ok(_) => {}
+        Err(err) if err.downcast_ref::<Status>().is_some_and(|status| status.code() == Code::Aborted) => {
+            return Err(err_boxed_full(IDEMPOTENCY_KEY_IN_PROGRESS, idempotency_key));
+        }
+        Err(err) => return Err(err),
+    }
+
+    // 标记由本次请求写入, 读取 etag 供 complete/release 做条件写
+    let Some((_, etag)) = get_record(&store_name, &key).await? else {
+        return Err(err_boxed_full(IDEMPOTENCY_KEY_IN_PROGRESS, idempotency_key));
+    };
+    Ok(Idempotent::Proceed(IdempotencyGuard {
+        store_name,
+        key,
+        ttl_seconds: idempotency.ttl_seconds,
+        etag,
+        owner: marker.owner,
+        request_hash,
+    }))
+}
+
+/// key 按调用方隔离: JWT 的 sub, 其次为内部调用, 再次为 api key 的摘要
+fn caller(params: &Params) -> String {
+    let header = |name: &str| params.header.iter().find(|(k, _)| k.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_str());
+
+    if let Some(sub) = header(AuthHeader::XSGAuthJWT.lower_case_value()).and_then(jwt_subject) {
+        return format!("jwt:{}", sub);
+    }
+    if header(AuthHeader::XSGAuthInternal.lower_case_value()).is_some() {
+        return "internal".to_string();
+    }
+    match header(AuthHeader::XSGAuthApiKey.lower_case_value()) {
+        Some(api_key) => format!("api-key:{}", hex::encode(Sha256::digest(api_key.as_bytes()))),
+        None => "anonymous".to_string(),
+    }
+}
+
+async fn get_record(store_name: &str, key: &str) -> HttpResult<Option<(IdempotencyRecord, String)>> {
+    let state = get_dapr_grpc_client()
+        .await?
+        .get_state(GetStateRequest {
+            store_name: store_name.to_string(),
+            key: key.to_string(),
+            ..Default::default()
+        })
+        .await?
+        .into_inner();
+
+    if state.data.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some((serde_json::from_slice::<IdempotencyRecord>(&state.data)?, state.etag)))
+}
+
+async fn save_record(store_name: &str, key: &str, record: &IdempotencyRecord, etag: Option<String>, ttl_seconds: u64) -> HttpResult<()> {
+    let mut metadata = HashMap::<String, String>::new();
+    metadata.insert("ttlInSeconds".to_string(), ttl_seconds.to_string());
+
+    get_dapr_grpc_client()
+        .await?
+        .save_state(SaveStateRequest {
+            store_name: store_name.to_string(),
+            states: vec![StateItem {
+                key: key.to_string(),
+                value: serde_json::to_vec(record)?,
+                etag: etag.map(|value| Etag { value }),
+                metadata,
+                options: Some(StateOptions {
+                    concurrency: StateConcurrency::ConcurrencyFirstWrite as i32,
+                    ..Default::default()
+                }),
+            }],
+        })
+        .await?;
+    Ok(())
+}
+
+pub fn replay_http(record: IdempotencyRecord) -> Response<Either<body::Body, body::BodySt>> {
+    Response::builder()
+        .status(StatusCode::from_u16(record.status_code).unwrap_or(StatusCode::OK))
+        .header(header::CONTENT_TYPE, record.content_type.as_str())
+        .header(IDEMPOTENT_REPLAYED_HEADER, "true")
+        .extension(BizCode(record.biz_code))
+        .body(Either::Left(body::bytes(record.body_bytes())))
+        .unwrap()
+}
+
+/// 只记录 2xx 且非流式的响应, 其余情况释放标记
+pub async fn finish_http(
+    guard: Option<IdempotencyGuard>,
+    res: HttpResult<Response<Either<body::Body, body::BodySt>>>,
+) -> HttpResult<Response<Either<body::Body, body::BodySt>>> {
+    let Some(guard) = guard else {
+        return res;
+    };
+
+    let response = match res {
+        Ok(response) if response.status().is_success() => response,
+        res => {
+            guard.release().await;
+            return res;
+        }
+    };
+
+    let (parts, resp_body) = response.into_parts();
+    let bytes = match resp_body {
+        Either::Left(resp_body) => resp_body.collect().await?.to_bytes(),
+        Either::Right(resp_body) => {
+            guard.release().await;
+            return Ok(Response::from_parts(parts, Either::Right(resp_body)));
+        }
+    };
+
+    let content_type = parts
+        .headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    let biz_code = parts.extensions.get::<BizCode>().map(|c| c.0).unwrap_or_default();
+    guard.complete(parts.status.as_u16(), biz_code, content_type, &bytes).await;
+
+    Ok(Response::from_parts(parts, Either::Left(body::bytes(bytes))))
+}
+
+/// gRPC 的响应没有 http status, 取 biz code 对应的 biz result 的 status, 找不到时为 200
+async fn status_code(biz_code: u32) -> u16 {
+    BIZ_RESULT_MAP
+        .read()
+        .await
+        .values()
+        .find(|biz_res| biz_res.biz_code() == biz_code)
+        .map(|biz_res| biz_res.status_code())
+        .unwrap_or(StatusCode::OK.as_u16())
+}
+
+pub fn replay_grpc(record: IdempotencyRecord) -> tonic::Response<InvokeResponse> {
+    let mut response = tonic::Response::new(InvokeResponse {
+        content_type: record.content_type.clone(),
+        data: Some(prost_types::Any {
+            type_url: "".to_string(),
+            value: record.body_bytes(),
+        }),
+        headers: HashMap::<String, String>::new(),
+    });
+    response.extensions_mut().insert(BizCode(record.biz_code));
+    response.metadata_mut().insert(IDEMPOTENT_REPLAYED_HEADER, MetadataValue::from_static("true"));
+    response
+}
+
+pub async fn finish_grpc(guard: Option<IdempotencyGuard>, res: GrpcResult<tonic::Response<InvokeResponse>>) -> GrpcResult<tonic::Response<InvokeResponse>> {
+    let Some(guard) = guard else {
+        return res;
+    };
+
+    match &res {
+        Ok(response) => {
+            let invoke_response = response.get_ref();
+            let data = invoke_response.data.as_ref().map(|any| any.value.clone()).unwrap_or_default();
+            let biz_code = response.extensions().get::<BizCode>().map(|c| c.0).unwrap_or_default();
+            let status = status_code(biz_code).await;
+            guard.complete(status, biz_code, invoke_response.content_type.clone(), &data).await;
+        }
+        Err(_) => guard.release().await,
+    }
+    res
+}
+
+pub fn replay_native_grpc(record: IdempotencyRecord) -> tonic::Response<Vec<u8>> {
+    let mut response = tonic::Response::new(record.body_bytes());
+    response.extensions_mut().insert(BizCode(record.biz_code));
+    response.metadata_mut().insert(IDEMPOTENT_REPLAYED_HEADER, MetadataValue::from_static("true"));
+    response
+}
+
+pub async fn finish_native_grpc(guard: Option<IdempotencyGuard>, res: GrpcResult<tonic::Response<Vec<u8>>>) -> GrpcResult<tonic::Response<Vec<u8>>> {
+    let Some(guard) = guard else {
+        return res;
+    };
+
+    match &res {
+        Ok(response) => {
+            let biz_code = response.extensions().get::<BizCode>().map(|c| c.0).unwrap_or_default();
+            let status = status_code(biz_code).await;
+            guard.complete(status, biz_code, String::new(), response.get_ref()).await;
+        }
+        Err(_) => guard.release().await,
+    }
+    res
+}
@@ -33,6 +33,38 @@ biz_result! {
     (DATA_ERROR, 500, 999927, "data error");
     (AUTH_ERROR, 401, 999928, "auth error");
     (INTERNAL_AUTH_TAG_NOT_SET, 500, 999929, "internal auth tag not set");
+    (REQUEST_BODY_TOO_LARGE, 413, 999930, "request body too large");
+    (NOT_ACCEPTABLE, 406, 999931, "not acceptable");
+    (TOO_MANY_REQUESTS, 429, 999932, "too many requests");
+    (IDEMPOTENCY_KEY_IN_PROGRESS, 409, 999933, "idempotency key in progress");
+    (METHOD_NOT_ALLOWED, 405, 999934, "method not allowed");
+    (IDEMPOTENCY_KEY_REUSED, 422, 999935, "idempotency key reused");
 }
 
 struct InnerConfigForSelfUse();
+
+#[cfg(test)]
+mod tests {
+    use sg_sdk_macro::biz_result_handler;
+
+    use super::InnerConfigForSelfUse;
+    use crate::{biz_result, util::BizResult, HttpResult};
+
+    /// 前缀取 9999, 注册后的 biz code 与 inner biz result 相同
+    #[biz_result_handler(9999, <TEST_BIZ_RES, 500, 99, "test biz result">)]
+    struct TestConfig();
+
+    #[tokio::test]
+    async fn every_inner_biz_result_is_registered_by_handler() {
+        TestConfig::insert_biz_result().await.unwrap();
+
+        for inner in InnerConfigForSelfUse::BIZ_RESULTS {
+            let Ok(registered) = BizResult::from(inner.name()).await else {
+                panic!("{} is not registered by #[biz_result_handler]", inner.name());
+            };
+            assert_eq!(registered.status_code(), inner.status_code(), "{}", inner.name());
+            assert_eq!(registered.biz_code(), inner.biz_code(), "{}", inner.name());
+            assert_eq!(registered.message(), inner.message(), "{}", inner.name());
+        }
+    }
+}
@@ -1,35 +1,71 @@
 use std::collections::HashMap;
 use std::env;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
 
-use tokio::sync::RwLock;
+use tokio::sync::{RwLock, Semaphore};
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
 use tonic::Status;
 use tracing::{error, info, warn};
 
 use crate::{
+    binding::{BindingHandler, InputBinding},
+    health::ReadinessCheck,
+    idempotency::Idempotency,
+    metrics::Metrics,
     model::{DaprConfig, ExtraParamMap, FunctionContextV1beta1, FunctionContextV1beta2},
+    openapi::ApiModel,
+    pubsub::{Topic, TopicHandler},
+    rate_limit::{MemoryRateLimitStore, RateLimit, RateLimitStore},
+    redact::DenyList,
+    router::Router,
+    shutdown::ShutdownHook,
+    traits::{Middleware, Plugin},
     util::{BizResult, URI},
 };
 
+pub mod binding;
+pub mod codec;
 pub mod config;
 pub mod context_extension;
 pub mod dapr_resp_resolve;
 pub mod daprs;
+pub mod health;
+pub mod idempotency;
 pub mod inner_biz_result;
 pub mod log;
 pub mod macros;
+pub mod metrics;
+pub mod middleware;
 pub mod model;
+pub mod native_grpc;
 pub mod nullable_to_vec;
+pub mod openapi;
+pub mod plugin;
+pub mod proto;
+pub mod pubsub;
+pub mod rate_limit;
+pub mod redact;
+pub mod router;
+pub mod self_check;
 pub mod sql_builder;
+pub mod server;
+pub mod shutdown;
 pub mod start;
+pub mod status;
 pub mod stringify_on_num;
+pub mod telemetry;
 pub mod traits;
 pub mod util;
 
 pub mod body {
     use std::convert::Infallible;
 
-    use http_body_util::{combinators::BoxBody, Either, Empty, Full};
-    use hyper::body::Bytes;
+    use http_body_util::{combinators::BoxBody, Either, Empty, Full, Limited};
+    use hyper::body::{Bytes, Incoming};
+
+    pub type ReqBody = Limited<Incoming>;
 
     pub type Body = Either<Empty<Bytes>, Full<Bytes>>;
 
@@ -58,11 +94,51 @@ lazy_static! {
     pub static ref SERVICE_ID: i64 = 6999453093112840195;
     pub static ref SKIP_AUTH_IFS: RwLock<Vec<String>> = RwLock::new(vec![]);
     pub static ref INTERNAL_AUTH_TAG: RwLock<Option<String>> = RwLock::new(None);
-    pub static ref URI_REGEX_MAP: RwLock<HashMap<URI, regex::Regex>> = RwLock::new(HashMap::<URI, regex::Regex>::new());
+    pub static ref ROUTER: RwLock<Router> = RwLock::new(Router::default());
     pub static ref URIS: RwLock<HashMap<String, URI>> = RwLock::new(HashMap::<String, URI>::new());
     pub static ref URI_HANDLERS: RwLock<Vec<(String, String)>> = RwLock::new(Vec::<(String, String)>::new());
     pub static ref BIZ_RESULT_MAP: RwLock<HashMap<String, BizResult<'static>>> = RwLock::new(HashMap::<String, BizResult>::new());
+    pub static ref TOPICS: RwLock<HashMap<String, (Topic, TopicHandler)>> = RwLock::new(HashMap::<String, (Topic, TopicHandler)>::new());
+    pub static ref INPUT_BINDINGS: RwLock<HashMap<String, (InputBinding, BindingHandler)>> =
+        RwLock::new(HashMap::<String, (InputBinding, BindingHandler)>::new());
+    pub static ref MIDDLEWARES: RwLock<Vec<Arc<dyn Middleware>>> = RwLock::new(Vec::<Arc<dyn Middleware>>::new());
+    pub static ref PLUGINS: RwLock<HashMap<String, Arc<dyn Plugin>>> = RwLock::new(HashMap::<String, Arc<dyn Plugin>>::new());
+    pub static ref READINESS_CHECKS: RwLock<Vec<(String, ReadinessCheck)>> = RwLock::new(vec![
+        (String::from("dapr_sidecar"), health::check_dapr_sidecar as ReadinessCheck),
+        (String::from("dapr_components"), health::check_dapr_components as ReadinessCheck),
+    ]);
+    pub static ref IDEMPOTENT_URIS: RwLock<HashMap<String, Idempotency>> = RwLock::new(HashMap::<String, Idempotency>::new());
+    pub static ref RATE_LIMITS: RwLock<HashMap<String, RateLimit>> = RwLock::new(HashMap::<String, RateLimit>::new());
+    pub static ref RATE_LIMIT_STORE: RwLock<Arc<dyn RateLimitStore>> = RwLock::new(Arc::new(MemoryRateLimitStore::default()));
+    pub static ref RATE_LIMIT_SEMAPHORES: RwLock<HashMap<String, Arc<Semaphore>>> = RwLock::new(HashMap::<String, Arc<Semaphore>>::new());
+    pub static ref RATE_LIMIT_TRUSTED_PROXIES: RwLock<Vec<IpAddr>> = RwLock::new(Vec::<IpAddr>::new());
+    pub static ref REDACT_DENY_LIST: DenyList = DenyList::from_env();
+    pub static ref METRICS: Metrics = match Metrics::new() {
+        Ok(metrics) => metrics,
+        Err(err) => {
+            error!("init metrics error: {}", err);
+            panic!("init METRICS error!")
+        }
+    };
+    pub static ref SHUTDOWN_TOKEN: CancellationToken = CancellationToken::new();
+    pub static ref TASK_TRACKER: TaskTracker = TaskTracker::new();
+    pub static ref SHUTDOWN_HOOKS: RwLock<Vec<(String, ShutdownHook)>> = RwLock::new(Vec::<(String, ShutdownHook)>::new());
+    pub static ref DRAIN_TIMEOUT: RwLock<Duration> = {
+        let secs = match env::var("SHUTDOWN_DRAIN_TIMEOUT") {
+            Ok(val) => match val.parse::<u64>() {
+                Ok(v) => v,
+                Err(_) => {
+                    warn!("env SHUTDOWN_DRAIN_TIMEOUT format error, will use 30s default.");
+                    30
+                }
+            },
+            Err(_) => 30,
+        };
+        RwLock::new(Duration::from_secs(secs))
+    };
     pub static ref INCOME_PARAM_MAP: RwLock<HashMap<String, ExtraParamMap>> = RwLock::new(HashMap::<String, ExtraParamMap>::new());
+    pub static ref API_MODELS: RwLock<HashMap<String, ApiModel>> = RwLock::new(HashMap::<String, ApiModel>::new());
+    pub static ref PROTO_TYPES: RwLock<HashMap<String, &'static str>> = RwLock::new(HashMap::<String, &'static str>::new());
     pub static ref DAPR_CONFIG: DaprConfig = {
         match env::var("DAPR_CONFIG") {
             Ok(val) => match serde_json::from_str::<DaprConfig>(&val) {
@@ -1,14 +1,75 @@
-use tracing::subscriber::set_global_default;
-use tracing_subscriber::filter::EnvFilter;
-
-pub fn init_log() {
-    let subscriber = tracing_subscriber::fmt()
-        .compact()
-        .with_file(false)
-        .with_line_number(true)
-        .with_thread_ids(false)
-        .with_env_filter(EnvFilter::from_default_env())
-        .finish();
-
-    set_global_default(subscriber).expect("set global subscriber fail");
+use std::{env, future::Future};
+
+use tracing::{subscriber::set_global_default, Span};
+use tracing_subscriber::{filter::EnvFilter, layer::SubscriberExt};
+
+use crate::{model::Params, telemetry, HttpResult};
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+pub const CALLER_APP_ID_HEADER: &str = "dapr-caller-app-id";
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// LOG_FORMAT=json 时输出 JSON 日志, 默认为 compact 文本; pluginsTracing 配置错误时返回错误
+pub fn init_log() -> HttpResult<()> {
+    let tracer = telemetry::init_tracer()?;
+
+    let json = matches!(env::var("LOG_FORMAT").as_deref(), Ok("json"));
+
+    let json_layer = json.then(|| {
+        tracing_subscriber::fmt::layer()
+            .json()
+            .flatten_event(true)
+            .with_current_span(true)
+            .with_span_list(true)
+            .with_file(false)
+            .with_line_number(true)
+    });
+    let text_layer = (!json).then(|| {
+        tracing_subscriber::fmt::layer()
+            .compact()
+            .with_file(false)
+            .with_line_number(true)
+            .with_thread_ids(false)
+    });
+
+    let subscriber = tracing_subscriber::registry()
+        .with(EnvFilter::from_default_env())
+        .with(json_layer)
+        .with(text_layer)
+        .with(tracer.map(|tracer| tracing_opentelemetry::layer().with_tracer(tracer)));
+
+    set_global_default(subscriber)?;
+    Ok(())
+}
+
+/// 解析参数后将 uri_name, saga_id 记录到当前的 request span
+pub fn record_params(params: &Params) {
+    let span = Span::current();
+    span.record("uri_name", params.uri.as_str());
+    if let Some(saga_id) = params.header.get("saga_id") {
+        span.record("saga_id", saga_id.as_str());
+    }
+}
+
+/// 优先使用当前请求已确定的 request id, 其次接受请求头中的值, 都没有时生成新的
+pub fn request_id(header_value: Option<&str>) -> String {
+    if let Some(request_id) = current_request_id() {
+        return request_id;
+    }
+    match header_value {
+        Some(v) if !v.is_empty() && v.len() <= 128 && v.bytes().all(|b| b.is_ascii_graphic()) => v.to_string(),
+        _ => uuid::Uuid::new_v4().to_string(),
+    }
+}
+
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(|request_id| request_id.clone()).ok()
+}
+
+/// 在 future 内可通过 current_request_id 取得 request id, 用于出站请求的传递
+pub async fn with_request_id<F: Future>(request_id: String, f: F) -> F::Output {
+    REQUEST_ID.scope(request_id, f).await
 }
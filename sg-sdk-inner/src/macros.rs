@@ -12,6 +12,8 @@ macro_rules! biz_result {
 
         #[allow(dead_code)]
         impl $acceptor {
+            const BIZ_RESULTS: &'static [crate::util::BizResult<'static>] = &[$($konst,)*];
+
             async fn insert_biz_result() -> HttpResult<()> {
                 $(
                     crate::util::insert_biz_result($konst).await?;
@@ -90,10 +92,204 @@ macro_rules! uri {
     }
 }
 
+#[macro_export]
+macro_rules! topic {
+    (
+        $acceptor:ident,
+        $(
+            ($konst:ident, $pubsub_name:expr, $topic:expr, $route:expr, $fn_name:ident);
+        )*
+    ) => {
+        $(
+            pub const $konst: crate::pubsub::Topic = crate::pubsub::Topic($pubsub_name, $topic, $route, stringify!($konst));
+        )*
+
+        impl $acceptor {
+            async fn insert_topic() -> HttpResult<()> {
+                $(
+                    crate::pubsub::insert_topic($konst, |params| Box::pin(async move { $fn_name(&params).await.map(|_| ()) })).await?;
+                )*
+                Ok(())
+            }
+        }
+    }
+}
+
+#[macro_export]
+macro_rules! input_binding {
+    (
+        $acceptor:ident,
+        $(
+            ($konst:ident, $binding_name:expr, $fn_name:ident);
+        )*
+    ) => {
+        $(
+            pub const $konst: crate::binding::InputBinding = crate::binding::InputBinding($binding_name, stringify!($konst));
+        )*
+
+        impl $acceptor {
+            async fn insert_input_binding() -> HttpResult<()> {
+                $(
+                    crate::binding::insert_input_binding($konst, |params| Box::pin(async move { $fn_name(&params).await })).await?;
+                )*
+                Ok(())
+            }
+        }
+    }
+}
+
+#[macro_export]
+macro_rules! rate_limit {
+    (
+        $acceptor:ident,
+        $(
+            ($konst:ident, $rate:expr, $burst:expr, $max_in_flight:expr, $key:ident);
+        )*
+    ) => {
+        impl $acceptor {
+            async fn insert_rate_limit() -> HttpResult<()> {
+                $(
+                    crate::rate_limit::insert_rate_limit(crate::rate_limit::RateLimit::new($konst, $rate, $burst, $max_in_flight, crate::rate_limit::RateLimitKey::$key)).await?;
+                )*
+                Ok(())
+            }
+        }
+    }
+}
+
+#[macro_export]
+macro_rules! idempotent {
+    (
+        $acceptor:ident,
+        $(
+            ($konst:ident, $store_name:expr, $ttl_seconds:expr $(, $lease_seconds:expr)?);
+        )*
+    ) => {
+        impl $acceptor {
+            async fn insert_idempotency() -> HttpResult<()> {
+                $(
+                    crate::idempotency::insert_idempotency(crate::idempotency::Idempotency::new($konst, $store_name, $ttl_seconds)$(.lease_seconds($lease_seconds))?).await?;
+                )*
+                Ok(())
+            }
+        }
+    }
+}
+
+#[macro_export]
+macro_rules! api_model {
+    (
+        $acceptor:ident,
+        $(
+            ($konst:ident, $input:ident, $output:ident);
+        )*
+    ) => {
+        impl $acceptor {
+            async fn insert_api_model() -> HttpResult<()> {
+                $(
+                    crate::openapi::insert_api_model(crate::openapi::ApiModel::new(
+                        $konst,
+                        stringify!($input),
+                        <$input as ModelTrait>::field_names(),
+                        <$input as ModelTrait>::json_schema(),
+                        stringify!($output),
+                        <$output as ModelTrait>::json_schema(),
+                    ))
+                    .await?;
+                    crate::proto::insert_proto_type(stringify!($input), <$input as ModelTrait>::proto_message()).await?;
+                    crate::proto::insert_proto_type(stringify!($output), <$output as ModelTrait>::proto_message()).await?;
+                )*
+                Ok(())
+            }
+        }
+    }
+}
+
+#[macro_export]
+macro_rules! proto_types {
+    (
+        $acceptor:ident,
+        messages: ($($message:ident$(,)?)*),
+        enums: ($($enumeration:ident$(,)?)*)$(,)?
+    ) => {
+        impl $acceptor {
+            async fn insert_proto_types() -> HttpResult<()> {
+                $(
+                    crate::proto::insert_proto_type(stringify!($message), <$message as ModelTrait>::proto_message()).await?;
+                )*
+                $(
+                    crate::proto::insert_proto_type(stringify!($enumeration), $enumeration::proto_enum()).await?;
+                )*
+                Ok(())
+            }
+        }
+    }
+}
+
+#[macro_export]
+macro_rules! shutdown_hook {
+    ($acceptor:ident,($($fn_name:ident$(,)?)*)) => {
+        impl $acceptor {
+            async fn insert_shutdown_hook() -> HttpResult<()> {
+                $(
+                    crate::shutdown::insert_shutdown_hook(stringify!($fn_name), || Box::pin(async move { $fn_name().await })).await?;
+                )*
+                Ok(())
+            }
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! readiness_check {
+    ($acceptor:ident,($($fn_name:ident$(,)?)*)) => {
+        impl $acceptor {
+            async fn insert_readiness_check() -> HttpResult<()> {
+                $(
+                    crate::health::insert_readiness_check(stringify!($fn_name), || Box::pin(async move { $fn_name().await })).await?;
+                )*
+                Ok(())
+            }
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! middleware {
+    ($acceptor:ident,($($middleware:expr$(,)?)*)) => {
+        impl $acceptor {
+            async fn insert_middleware() -> HttpResult<()> {
+                $(
+                    crate::middleware::insert_middleware(stringify!($middleware), std::sync::Arc::new($middleware)).await?;
+                )*
+                Ok(())
+            }
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! plugin {
+    ($acceptor:ident,($($plugin:expr$(,)?)*)) => {
+        impl $acceptor {
+            async fn insert_plugin() -> HttpResult<()> {
+                $(
+                    crate::plugin::insert_plugin(std::sync::Arc::new($plugin)).await?;
+                )*
+                Ok(())
+            }
+        }
+    };
+}
+
 #[macro_export]
 macro_rules! generate_http_dispatcher {
     ($acceptor:ident,[$(($uri_name:ident, $fn_name:ident)$(,)?)*]) => {
         impl HttpRequestDispatcherTrait for $acceptor {
+            fn uri_names() -> &'static [&'static str] {
+                &[$(stringify!($uri_name)),*]
+            }
+
             async fn do_http_dispatch(params: Params) -> HttpResult<hyper::Response<Either<body::Body, body::BodySt>>> {
                 match params.uri.as_str() {
                     $(
@@ -117,10 +313,34 @@ macro_rules! generate_http_dispatcher {
     };
 }
 
+#[macro_export]
+macro_rules! generate_native_grpc_dispatcher {
+    ($acceptor:ident,[$(($uri_name:ident, $fn_name:ident)$(,)?)*]) => {
+        impl NativeGrpcDispatcherTrait for $acceptor {
+            async fn do_native_grpc_dispatch(params: Params) -> GrpcResult<tonic::Response<Vec<u8>>> {
+                match params.uri.as_str() {
+                    $(
+                        stringify!($uri_name) => handle_native_grpc($fn_name(&params).await, &params).await,
+                    )*
+
+                    _ => {
+                        error!("[request begin] error: uri match nothing");
+                        return GrpcResult::Err(crate::status::biz_status(URI_NOT_MATCH, None));
+                    }
+                }
+            }
+        }
+    };
+}
+
 #[macro_export]
 macro_rules! generate_grpc_dispatcher {
     ($acceptor:ident,[$(($uri_name:ident, $fn_name:ident)$(,)?)*]) => {
         impl GrpcRequestDispatcherTrait for $acceptor {
+            fn uri_names() -> &'static [&'static str] {
+                &[$(stringify!($uri_name)),*]
+            }
+
             async fn do_grpc_dispatch(params: Params) -> GrpcResult<tonic::Response<InvokeResponse>> {
                 match params.uri.as_str() {
                     $(
@@ -129,7 +349,7 @@ macro_rules! generate_grpc_dispatcher {
 
                     _ => {
                         error!("[request begin] error: uri match nothing");
-                        return GrpcResult::Err(tonic::Status::internal(URI_NOT_MATCH.message()));
+                        return GrpcResult::Err(crate::status::biz_status(URI_NOT_MATCH, None));
                     }
                 }
             }
@@ -0,0 +1,148 @@
+use std::{future::Future, time::Instant};
+
+use http_body_util::Either;
+use hyper::{body::Incoming, header, Response, StatusCode};
+use prometheus::{Encoder, HistogramOpts, HistogramVec, IntCounterVec, Opts, Registry, TextEncoder};
+use tracing::error;
+
+use crate::{
+    body,
+    model::{Action, DaprBuildBlockType, DaprOperationType},
+    GrpcResult, HttpResult, METRICS,
+};
+
+pub const METRICS_PATH: &str = "/metrics";
+
+/// 写入 response extensions, 记录请求指标时作为 biz_code label
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BizCode(pub u32);
+
+pub struct Metrics {
+    registry: Registry,
+    requests_total: IntCounterVec,
+    request_duration: HistogramVec,
+    dapr_calls_total: IntCounterVec,
+    dapr_call_errors: IntCounterVec,
+    dapr_call_duration: HistogramVec,
+}
+
+impl Metrics {
+    pub fn new() -> HttpResult<Self> {
+        let registry = Registry::new();
+
+        let request_labels = ["uri_name", "action", "biz_code"];
+        let requests_total = IntCounterVec::new(Opts::new("sg_requests_total", "Total requests handled"), &request_labels)?;
+        let request_duration = HistogramVec::new(
+            HistogramOpts::new("sg_request_duration_seconds", "Request handling latency in seconds"),
+            &request_labels,
+        )?;
+
+        let dapr_labels = ["bb_type", "bo_type", "component"];
+        let dapr_calls_total = IntCounterVec::new(Opts::new("sg_dapr_calls_total", "Total dapr calls"), &dapr_labels)?;
+        let dapr_call_errors = IntCounterVec::new(Opts::new("sg_dapr_call_errors_total", "Failed dapr calls"), &dapr_labels)?;
+        let dapr_call_duration = HistogramVec::new(
+            HistogramOpts::new("sg_dapr_call_duration_seconds", "Dapr call latency in seconds"),
+            &dapr_labels,
+        )?;
+
+        registry.register(Box::new(requests_total.clone()))?;
+        registry.register(Box::new(request_duration.clone()))?;
+        registry.register(Box::new(dapr_calls_total.clone()))?;
+        registry.register(Box::new(dapr_call_errors.clone()))?;
+        registry.register(Box::new(dapr_call_duration.clone()))?;
+
+        Ok(Metrics {
+            registry,
+            requests_total,
+            request_duration,
+            dapr_calls_total,
+            dapr_call_errors,
+            dapr_call_duration,
+        })
+    }
+
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+}
+
+pub fn metrics_http() -> Response<Either<body::Body, body::BodySt>> {
+    let encoder = TextEncoder::new();
+    let mut buffer = Vec::<u8>::new();
+
+    if let Err(err) = encoder.encode(&METRICS.registry.gather(), &mut buffer) {
+        error!("encode metrics error: {}", err);
+        return Response::builder()
+            .status(StatusCode::INTERNAL_SERVER_ERROR)
+            .body(Either::Left(body::empty()))
+            .unwrap();
+    }
+
+    Response::builder()
+        .header(header::CONTENT_TYPE, encoder.format_type())
+        .body(Either::Left(body::bytes(buffer)))
+        .unwrap()
+}
+
+fn observe_request(uri_name: &str, action: Option<&Action>, biz_code: Option<u32>, started: Instant) {
+    let action = action.map(|a| format!("{:?}", a)).unwrap_or("None".to_string());
+    let biz_code = biz_code.map(|c| c.to_string()).unwrap_or("unknown".to_string());
+    let labels = [uri_name, action.as_str(), biz_code.as_str()];
+
+    METRICS.requests_total.with_label_values(&labels).inc();
+    METRICS.request_duration.with_label_values(&labels).observe(started.elapsed().as_secs_f64());
+}
+
+pub fn observe_http(uri_name: &str, action: Option<&Action>, res: &HttpResult<Response<Either<body::Body, body::BodySt>>>, started: Instant) {
+    let biz_code = match res {
+        Ok(response) => response.extensions().get::<BizCode>().map(|c| c.0),
+        Err(_) => None,
+    };
+    observe_request(uri_name, action, biz_code, started);
+}
+
+pub fn observe_grpc<T>(uri_name: &str, action: Option<&Action>, res: &GrpcResult<tonic::Response<T>>, started: Instant) {
+    let biz_code = match res {
+        Ok(response) => response.extensions().get::<BizCode>().map(|c| c.0),
+        Err(_) => None,
+    };
+    observe_request(uri_name, action, biz_code, started);
+}
+
+fn observe_dapr_call(bb_type: &DaprBuildBlockType, bo_type: &DaprOperationType, component: &str, failed: bool, started: Instant) {
+    let bb_type = format!("{:?}", bb_type);
+    let bo_type = format!("{:?}", bo_type);
+    let labels = [bb_type.as_str(), bo_type.as_str(), component];
+
+    METRICS.dapr_calls_total.with_label_values(&labels).inc();
+    METRICS.dapr_call_duration.with_label_values(&labels).observe(started.elapsed().as_secs_f64());
+    if failed {
+        METRICS.dapr_call_errors.with_label_values(&labels).inc();
+    }
+}
+
+/// 统计 gRPC 方式的 dapr 调用, Err 计为失败
+pub async fn observe_dapr<T, E, F>(bb_type: DaprBuildBlockType, bo_type: DaprOperationType, component: &str, call: F) -> Result<T, E>
+where
+    F: Future<Output = Result<T, E>>,
+{
+    let started = Instant::now();
+    let res = call.await;
+    observe_dapr_call(&bb_type, &bo_type, component, res.is_err(), started);
+    res
+}
+
+/// 统计 HTTP 方式的 dapr 调用, Err 及非 2xx 的响应计为失败
+pub async fn observe_dapr_http<F>(bb_type: DaprBuildBlockType, bo_type: DaprOperationType, component: &str, call: F) -> HttpResult<Response<Incoming>>
+where
+    F: Future<Output = HttpResult<Response<Incoming>>>,
+{
+    let started = Instant::now();
+    let res = call.await;
+    let failed = match &res {
+        Ok(response) => !response.status().is_success(),
+        Err(_) => true,
+    };
+    observe_dapr_call(&bb_type, &bo_type, component, failed, started);
+    res
+}
@@ -0,0 +1,51 @@
+use std::sync::Arc;
+
+use dapr::appcallback::InvokeResponse;
+use http_body_util::Either;
+use hyper::Response;
+use tracing::info;
+
+use crate::{body, model::Params, traits::Middleware, HttpResult, MIDDLEWARES};
+
+pub async fn insert_middleware(name: &str, middleware: Arc<dyn Middleware>) -> HttpResult<()> {
+    info!("set middleware: {}", name);
+    let mut middlewares = MIDDLEWARES.write().await;
+    middlewares.push(middleware);
+    Ok(())
+}
+
+pub async fn middlewares() -> Vec<Arc<dyn Middleware>> {
+    MIDDLEWARES.read().await.clone()
+}
+
+pub async fn before(middlewares: &[Arc<dyn Middleware>], params: &mut Params) -> HttpResult<()> {
+    for middleware in middlewares.iter() {
+        middleware.before(params).await?;
+    }
+    Ok(())
+}
+
+pub async fn after_http(
+    middlewares: &[Arc<dyn Middleware>],
+    params: &Params,
+    response: &mut Response<Either<body::Body, body::BodySt>>,
+) -> HttpResult<()> {
+    for middleware in middlewares.iter().rev() {
+        middleware.after_http(params, response).await?;
+    }
+    Ok(())
+}
+
+pub async fn after_grpc(middlewares: &[Arc<dyn Middleware>], params: &Params, response: &mut tonic::Response<InvokeResponse>) -> HttpResult<()> {
+    for middleware in middlewares.iter().rev() {
+        middleware.after_grpc(params, response).await?;
+    }
+    Ok(())
+}
+
+pub async fn after_native_grpc(middlewares: &[Arc<dyn Middleware>], params: &Params, response: &mut tonic::Response<Vec<u8>>) -> HttpResult<()> {
+    for middleware in middlewares.iter().rev() {
+        middleware.after_native_grpc(params, response).await?;
+    }
+    Ok(())
+}
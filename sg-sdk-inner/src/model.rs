@@ -6,11 +6,18 @@ use prost::Message;
 use serde::{Deserialize, Serialize};
 use sg_sdk_macro::Model;
 use sg_sdk_macro::ModelValidate;
-use std::{collections::HashMap, fmt::Debug, str::FromStr};
+use std::{collections::HashMap, fmt::Debug, net::IpAddr, str::FromStr};
 use validator::Validate;
 use validator_derive::Validate;
 
-use crate::{inner_biz_result::*, traits::*, util::*, HttpResult, *};
+use crate::{
+    codec::Codec,
+    inner_biz_result::*,
+    redact::{mask_headers, mask_json_bytes, mask_metadata, RedactedBody, RedactedHeaders, RedactedQuery, RedactedValues},
+    traits::*,
+    util::*,
+    HttpResult, *,
+};
 
 #[derive(PartialEq, Eq, Serialize, Deserialize, Clone, Reflect)]
 pub struct Res<T> {
@@ -54,14 +61,43 @@ impl PageInfo {
     // }
 }
 
-#[derive(PartialEq, Eq, Debug, Validate, Clone, Default)]
+#[derive(PartialEq, Eq, Validate, Clone, Default)]
 pub struct Params {
     pub header: HashMap<String, String>,
     pub path_param: HashMap<u8, String>,
+    /// 路由模板中命名的 path 参数
+    pub named_path_param: HashMap<String, String>,
+    /// 每个 key 的第一个值, 重复的 key 以第一个为准 (早期版本取最后一个), income_param! 中 HashMap 类型的 `name[key]` 也是如此;
+    /// 需要全部的值时使用 query_values
     pub query_param: HashMap<String, String>,
+    /// 重复的 key 按出现顺序保留全部的值
+    pub query_values: HashMap<String, Vec<String>>,
     pub body: Option<Vec<u8>>,
     pub uri: String,
     pub if_info: IfInfo,
+    pub request_id: String,
+    /// 分发前依据 Accept 确定的响应编码
+    pub codec: Codec,
+    /// 连接的对端地址, pubsub/binding 事件没有
+    pub remote_addr: Option<IpAddr>,
+}
+
+impl Debug for Params {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Params")
+            .field("header", &RedactedHeaders(&self.header))
+            .field("path_param", &self.path_param)
+            .field("named_path_param", &self.named_path_param)
+            .field("query_param", &RedactedQuery(&self.query_param))
+            .field("query_values", &RedactedQuery(&self.query_values))
+            .field("body", &RedactedBody(&self.body))
+            .field("uri", &self.uri)
+            .field("if_info", &self.if_info)
+            .field("request_id", &self.request_id)
+            .field("codec", &self.codec)
+            .field("remote_addr", &self.remote_addr)
+            .finish()
+    }
 }
 
 #[derive(PartialEq, Eq, Debug, Validate, Clone, Default)]
@@ -470,11 +506,14 @@ pub enum DaprOperationType {
 #[derive(Debug, Default)]
 pub struct ContextWrapper<I: ModelTrait + prost::Message + Default, O: ModelTrait + prost::Message, C: Clone> {
     pub saga_id: Option<String>,
+    pub request_id: String,
     pub uri_name: String,
     pub if_info: IfInfo,
     pub header: HashMap<String, String>,
     pub path_param: HashMap<u8, String>,
+    pub named_path_param: HashMap<String, String>,
     pub query_param: HashMap<String, String>,
+    pub query_values: HashMap<String, Vec<String>>,
     pub input: I,
     pub inputs: Vec<I>,
     pub exec_name: Option<String>,
@@ -485,12 +524,13 @@ pub struct ContextWrapper<I: ModelTrait + prost::Message + Default, O: ModelTrai
     pub inner_context: C,
     pub form_data: Option<Vec<FormDataParam>>,
     pub response_header: HashMap<String, String>,
+    pub binding_response: BindingEventResponse,
 }
 
 #[derive(PartialEq, Eq, Serialize, Deserialize, Clone, Reflect, Model, Validate, ModelValidate, prost::Message)]
 pub struct EmptyInnerContext {}
 
-#[derive(PartialEq, Serialize, Deserialize, Debug, Clone, Default)]
+#[derive(PartialEq, Serialize, Deserialize, Clone, Default)]
 pub struct DaprRequest {
     pub _dapr_config: Option<DaprComponentInfo>,
     pub invoke_service: Option<InvokeServiceRequest>,
@@ -510,6 +550,61 @@ pub struct DaprRequest {
     pub get_configuration: Option<GetConfigurationRequest>,
 }
 
+/// metadata, header 及 JSON 数据中 deny-list 的字段打码后打印
+impl Debug for DaprRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fn masked<T: Clone>(req: &Option<T>, mask: fn(&mut T)) -> Option<T> {
+            req.clone().map(|mut req| {
+                mask(&mut req);
+                req
+            })
+        }
+
+        fn mask_state_item(item: &mut StateItem) {
+            mask_metadata(&mut item.metadata);
+            mask_json_bytes(&mut item.value);
+        }
+
+        f.debug_struct("DaprRequest")
+            .field("_dapr_config", &self._dapr_config)
+            .field(
+                "invoke_service",
+                &masked(&self.invoke_service, |req| {
+                    if let Some(message) = req.message.as_mut() {
+                        mask_headers(&mut message.headers);
+                        if let Some(data) = message.data.as_mut() {
+                            mask_json_bytes(&mut data.value);
+                        }
+                    }
+                }),
+            )
+            .field("get_state", &masked(&self.get_state, |req| mask_metadata(&mut req.metadata)))
+            .field("get_bulk_state", &masked(&self.get_bulk_state, |req| mask_metadata(&mut req.metadata)))
+            .field("query_state", &masked(&self.query_state, |req| mask_metadata(&mut req.metadata)))
+            .field("save_state", &masked(&self.save_state, |req| req.states.iter_mut().for_each(mask_state_item)))
+            .field("transaction_state", &masked(&self.transaction_state, |req| mask_metadata(&mut req.metadata)))
+            .field("delete_state", &masked(&self.delete_state, |req| mask_metadata(&mut req.metadata)))
+            .field(
+                "delete_bulk_state",
+                &masked(&self.delete_bulk_state, |req| req.states.iter_mut().for_each(mask_state_item)),
+            )
+            .field("invoke_binding", &masked(&self.invoke_binding, |req| mask_metadata(&mut req.metadata)))
+            .field(
+                "invoke_binding_sql",
+                &masked(&self.invoke_binding_sql, |req| {
+                    mask_metadata(&mut req.metadata);
+                    mask_json_bytes(&mut req.data);
+                }),
+            )
+            .field("publish_event", &masked(&self.publish_event, |req| mask_metadata(&mut req.metadata)))
+            .field("publish_bulk_event", &masked(&self.publish_bulk_event, |req| mask_metadata(&mut req.metadata)))
+            .field("get_secret", &masked(&self.get_secret, |req| mask_metadata(&mut req.metadata)))
+            .field("get_bluk_secret", &masked(&self.get_bluk_secret, |req| mask_metadata(&mut req.metadata)))
+            .field("get_configuration", &masked(&self.get_configuration, |req| mask_metadata(&mut req.metadata)))
+            .finish()
+    }
+}
+
 impl DaprRequest {
     pub fn make_invoke_service(id: String, method: String, content_type: String, http_method: MethodEnum, querystring: String) -> HttpResult<Self> {
         let mut s: Self = Default::default();
@@ -537,7 +632,7 @@ pub struct EmptyDaprBody {}
 
 impl DaprBody for EmptyDaprBody {}
 
-#[derive(PartialEq, Serialize, Deserialize, Debug, Clone, Default)]
+#[derive(PartialEq, Serialize, Deserialize, Clone, Default)]
 pub struct DaprResponse {
     pub invoke_service: Option<InvokeResponse>,
     pub get_state: Option<GetStateResponse>,
@@ -551,6 +646,24 @@ pub struct DaprResponse {
     pub get_configuration: Option<GetConfigurationResponse>,
 }
 
+/// secret store 的返回值不打印, 只保留 key
+impl Debug for DaprResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DaprResponse")
+            .field("invoke_service", &self.invoke_service)
+            .field("get_state", &self.get_state)
+            .field("get_bulk_state", &self.get_bulk_state)
+            .field("query_state", &self.query_state)
+            .field("invoke_binding", &self.invoke_binding)
+            .field("invoke_binding_sql", &self.invoke_binding_sql)
+            .field("publish_bulk_event", &self.publish_bulk_event)
+            .field("get_secret", &self.get_secret.as_ref().map(|res| RedactedValues(&res.data)))
+            .field("get_bluk_secret", &self.get_bluk_secret.as_ref().map(|res| RedactedValues(&res.data)))
+            .field("get_configuration", &self.get_configuration)
+            .finish()
+    }
+}
+
 #[derive(PartialEq, Eq, Reflect, Serialize, Deserialize, Clone, prost::Message)]
 pub struct IfRes<T: ModelTrait + Message + Default> {
     #[prost(string, optional, tag = "1")]
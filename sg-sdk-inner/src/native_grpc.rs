@@ -0,0 +1,256 @@
+use std::{convert::Infallible, time::Instant};
+
+use bytes::{Buf, BufMut};
+use tonic::{
+    codec::{Codec, DecodeBuf, Decoder, EncodeBuf, Encoder},
+    codegen::{http, Body, BoxFuture, Context, Poll, Service, StdError},
+    metadata::MetadataValue,
+    server::{Grpc, NamedService, UnaryService},
+    Status,
+};
+use tracing::{debug, field, info_span, Instrument};
+
+use crate::{
+    idempotency::{self, Idempotent, Protocol},
+    log, metrics, middleware,
+    model::Params,
+    plugin, rate_limit, shutdown, status,
+    telemetry::{self, MetadataExtractor},
+    traits::NativeGrpcDispatcherTrait,
+    util::{auth_ict, catch_panic, parse_params_native},
+    GrpcResult,
+};
+
+/// 原生 gRPC 服务名, 方法名为 URI 的 name, 如 /sg.function.Function/QUERY_BY_APP_ID
+pub const SERVICE_NAME: &str = "sg.function.Function";
+
+/// 请求与响应均为 prost 编码后的原始字节, 由 handler 的 input/output model 自行解码
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RawCodec;
+
+impl Codec for RawCodec {
+    type Encode = Vec<u8>;
+    type Decode = Vec<u8>;
+    type Encoder = RawCodec;
+    type Decoder = RawCodec;
+
+    fn encoder(&mut self) -> Self::Encoder {
+        RawCodec
+    }
+
+    fn decoder(&mut self) -> Self::Decoder {
+        RawCodec
+    }
+}
+
+impl Encoder for RawCodec {
+    type Item = Vec<u8>;
+    type Error = Status;
+
+    fn encode(&mut self, item: Self::Item, dst: &mut EncodeBuf<'_>) -> Result<(), Self::Error> {
+        dst.put_slice(&item);
+        Ok(())
+    }
+}
+
+impl Decoder for RawCodec {
+    type Item = Vec<u8>;
+    type Error = Status;
+
+    fn decode(&mut self, src: &mut DecodeBuf<'_>) -> Result<Option<Self::Item>, Self::Error> {
+        Ok(Some(src.copy_to_bytes(src.remaining()).to_vec()))
+    }
+}
+
+/// 每个 URI 对应一个 unary RPC, 不经过 dapr AppCallback
+pub struct NativeGrpcService<NativeDispatcher: NativeGrpcDispatcherTrait + Send + Copy + 'static> {
+    max_decoding_message_size: Option<usize>,
+    _placeholder: Option<NativeDispatcher>,
+}
+
+impl<NativeDispatcher: NativeGrpcDispatcherTrait + Send + Copy + 'static> NativeGrpcService<NativeDispatcher> {
+    pub fn new() -> Self {
+        NativeGrpcService {
+            max_decoding_message_size: None,
+            _placeholder: None,
+        }
+    }
+
+    pub fn max_decoding_message_size(mut self, limit: usize) -> Self {
+        self.max_decoding_message_size = Some(limit);
+        self
+    }
+}
+
+impl<NativeDispatcher: NativeGrpcDispatcherTrait + Send + Copy + 'static> Default for NativeGrpcService<NativeDispatcher> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<NativeDispatcher: NativeGrpcDispatcherTrait + Send + Copy + 'static> Clone for NativeGrpcService<NativeDispatcher> {
+    fn clone(&self) -> Self {
+        NativeGrpcService {
+            max_decoding_message_size: self.max_decoding_message_size,
+            _placeholder: None,
+        }
+    }
+}
+
+impl<NativeDispatcher: NativeGrpcDispatcherTrait + Send + Copy + 'static> NamedService for NativeGrpcService<NativeDispatcher> {
+    const NAME: &'static str = SERVICE_NAME;
+}
+
+impl<NativeDispatcher, B> Service<http::Request<B>> for NativeGrpcService<NativeDispatcher>
+where
+    NativeDispatcher: NativeGrpcDispatcherTrait + Send + Copy + Sync + 'static,
+    B: Body + Send + 'static,
+    B::Error: Into<StdError> + Send + 'static,
+{
+    type Response = http::Response<tonic::body::BoxBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        let Some(uri_name) = method_name(req.uri().path()) else {
+            let status = Status::unimplemented(format!("unknown grpc method: {}", req.uri().path()));
+            return Box::pin(async move { Ok(status.to_http()) });
+        };
+        let uri_name = uri_name.to_string();
+        let max_decoding_message_size = self.max_decoding_message_size;
+
+        Box::pin(async move {
+            let mut grpc = Grpc::new(RawCodec).apply_max_message_size_config(max_decoding_message_size, None);
+            let unary = NativeUnary::<NativeDispatcher> { uri_name, _placeholder: None };
+            Ok(grpc.unary(unary, req).await)
+        })
+    }
+}
+
+/// 请求路径须为 /<SERVICE_NAME>/<method>, 返回其中的 method
+fn method_name(path: &str) -> Option<&str> {
+    path.strip_prefix("/")
+        .and_then(|path| path.strip_prefix(SERVICE_NAME))
+        .and_then(|path| path.strip_prefix("/"))
+        .filter(|method| !method.is_empty() && !method.contains("/"))
+}
+
+struct NativeUnary<NativeDispatcher: NativeGrpcDispatcherTrait + Send + Copy + 'static> {
+    uri_name: String,
+    _placeholder: Option<NativeDispatcher>,
+}
+
+impl<NativeDispatcher: NativeGrpcDispatcherTrait + Send + Copy + Sync + 'static> UnaryService<Vec<u8>> for NativeUnary<NativeDispatcher> {
+    type Response = Vec<u8>;
+    type Future = BoxFuture<tonic::Response<Vec<u8>>, Status>;
+
+    fn call(&mut self, request: tonic::Request<Vec<u8>>) -> Self::Future {
+        Box::pin(on_native_invoke::<NativeDispatcher>(self.uri_name.clone(), request))
+    }
+}
+
+async fn on_native_invoke<NativeDispatcher: NativeGrpcDispatcherTrait + Send + Copy + Sync>(
+    uri_name: String,
+    request: tonic::Request<Vec<u8>>,
+) -> GrpcResult<tonic::Response<Vec<u8>>> {
+    let metadata = |key: &str| request.metadata().get(key).and_then(|v| v.to_str().ok());
+    let request_id = log::request_id(metadata(log::REQUEST_ID_HEADER));
+    let span = info_span!(
+        "request",
+        otel.kind = "server",
+        rpc.service = SERVICE_NAME,
+        rpc.method = %uri_name,
+        request_id = %request_id,
+        caller_app_id = metadata(log::CALLER_APP_ID_HEADER),
+        uri_name = field::Empty,
+        saga_id = field::Empty,
+    );
+    telemetry::set_parent_from(&span, &MetadataExtractor(request.metadata()));
+
+    let metadata_value = MetadataValue::try_from(request_id.as_str());
+    let mut res = log::with_request_id(request_id, invoke_native::<NativeDispatcher>(uri_name, request))
+        .instrument(span)
+        .await;
+    if let Ok(value) = metadata_value {
+        match &mut res {
+            Ok(response) => response.metadata_mut().insert(log::REQUEST_ID_HEADER, value),
+            Err(status) => status.metadata_mut().insert(log::REQUEST_ID_HEADER, value),
+        };
+    }
+    res
+}
+
+async fn invoke_native<NativeDispatcher: NativeGrpcDispatcherTrait + Send + Copy + Sync>(
+    uri_name: String,
+    request: tonic::Request<Vec<u8>>,
+) -> GrpcResult<tonic::Response<Vec<u8>>> {
+    let _in_flight = shutdown::InFlightGuard::new();
+    let started = Instant::now();
+    debug!("native grpc request: {}, {} bytes", uri_name, request.get_ref().len());
+
+    let params = match parse_params_native(&uri_name, request).await {
+        Ok(params) => params,
+        Err(err) => {
+            let res = GrpcResult::Err(err);
+            metrics::observe_grpc("unknown", None, &res, started);
+            return res;
+        }
+    };
+
+    log::record_params(&params);
+    let action = params.if_info.action.clone();
+    let res = match catch_panic(dispatch_native::<NativeDispatcher>(params)).await {
+        Ok(res) => res,
+        Err(err) => GrpcResult::Err(status::err_to_status(err).await),
+    };
+    metrics::observe_grpc(&uri_name, Some(&action), &res, started);
+    res
+}
+
+/// 与 AppCallback 相同执行 middleware, 鉴权, 限流, pre plugins 及幂等检查, middleware 的 after 为 after_native_grpc
+async fn dispatch_native<NativeDispatcher: NativeGrpcDispatcherTrait + Send + Copy + Sync>(mut params: Params) -> GrpcResult<tonic::Response<Vec<u8>>> {
+    let middlewares = middleware::middlewares().await;
+    if let Err(err) = middleware::before(&middlewares, &mut params).await {
+        return GrpcResult::Err(status::err_to_status(err).await);
+    }
+
+    if let Err(err) = auth_ict(&mut params).await {
+        return GrpcResult::Err(status::err_to_status(err).await);
+    }
+
+    let _permit = match rate_limit::check(&params).await {
+        Ok(permit) => permit,
+        Err(err) => return GrpcResult::Err(status::err_to_status(err).await),
+    };
+
+    if let Err(err) = plugin::run_pre_plugins(&mut params).await {
+        return GrpcResult::Err(status::err_to_status(err).await);
+    }
+
+    if middlewares.is_empty() {
+        return dispatch_idempotent_native::<NativeDispatcher>(params).await;
+    }
+
+    let request_params = params.clone();
+    let mut response = dispatch_idempotent_native::<NativeDispatcher>(params).await?;
+    if let Err(err) = middleware::after_native_grpc(&middlewares, &request_params, &mut response).await {
+        return GrpcResult::Err(status::err_to_status(err).await);
+    }
+
+    Ok(response)
+}
+
+async fn dispatch_idempotent_native<NativeDispatcher: NativeGrpcDispatcherTrait + Send + Copy + Sync>(params: Params) -> GrpcResult<tonic::Response<Vec<u8>>> {
+    let guard = match idempotency::begin(&params, Protocol::NativeGrpc).await {
+        Ok(Idempotent::Skip) => None,
+        Ok(Idempotent::Replay(record)) => return Ok(idempotency::replay_native_grpc(record)),
+        Ok(Idempotent::Proceed(guard)) => Some(guard),
+        Err(err) => return GrpcResult::Err(status::err_to_status(err).await),
+    };
+
+    idempotency::finish_native_grpc(guard, NativeDispatcher::do_native_grpc_dispatch(params).await).await
+}
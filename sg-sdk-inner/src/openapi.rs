@@ -0,0 +1,394 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    env,
+};
+
+use http_body_util::Either;
+use hyper::{header, Method, Response, StatusCode};
+use serde_json::{json, Map, Value};
+use tracing::{debug, info, warn};
+
+use crate::{
+    body,
+    model::{AuthHeader, ExtraParamMap, ParamFrom, ParamType},
+    router::{self, DEFAULT_PARAM_PATTERN},
+    util::{BizResult, ResponseError, URI},
+    HttpResult, API_MODELS, BIZ_RESULT_MAP, FUNC_CONTEXT, INCOME_PARAM_MAP, ROUTER, SKIP_AUTH_IFS,
+};
+
+pub const OPENAPI_PATH: &str = "/openapi.json";
+const OPENAPI_VERSION: &str = "3.1.0";
+const ERROR_RES_SCHEMA: &str = "ErrorRes";
+const BINARY_OUTPUT: &str = "BinaryOutPut";
+
+/// 文档中列出的鉴权 header, 不含服务间调用使用的 X-SG-AUTH-INTERNAL
+const AUTH_HEADERS: [AuthHeader; 7] = [
+    AuthHeader::XSGAuthJWT,
+    AuthHeader::XSGAuthBasic,
+    AuthHeader::XSGAuthOAuth2,
+    AuthHeader::XSGAuthAksk,
+    AuthHeader::XSGAuthApiKey,
+    AuthHeader::XSGAuthDigestAuth,
+    AuthHeader::XSGAuthOIDC,
+];
+
+/// URI 的 input/output model 及其 JSON Schema, 由 api_model! 注册
+#[derive(Debug, Clone)]
+pub struct ApiModel {
+    pub uri: URI,
+    pub input_name: &'static str,
+    pub input_fields: &'static [&'static str],
+    pub input_schema: Value,
+    pub output_name: &'static str,
+    pub output_schema: Value,
+}
+
+impl ApiModel {
+    pub fn new(
+        uri: URI,
+        input_name: &'static str,
+        input_fields: &'static [&'static str],
+        input_schema: Value,
+        output_name: &'static str,
+        output_schema: Value,
+    ) -> Self {
+        ApiModel {
+            uri,
+            input_name,
+            input_fields,
+            input_schema,
+            output_name,
+            output_schema,
+        }
+    }
+}
+
+pub async fn insert_api_model(api_model: ApiModel) -> HttpResult<()> {
+    info!(
+        "set api model: {} => ({}, {})",
+        api_model.uri.name(),
+        api_model.input_name,
+        api_model.output_name
+    );
+    let mut api_models = API_MODELS.write().await;
+    match api_models.insert(api_model.uri.name().to_string(), api_model.clone()) {
+        None => {}
+        Some(_) => {
+            return Err(Box::new(ResponseError {
+                biz_res: format!("api model of uri '{}' is exist", api_model.uri.name()),
+                message: None,
+            }));
+        }
+    };
+    Ok(())
+}
+
+/// 输出为 BinaryOutPut 的 uri 不受 Accept 限制
+pub async fn is_binary_output(uri_name: &str) -> bool {
+    API_MODELS
+        .read()
+        .await
+        .get(uri_name)
+        .is_some_and(|api_model| api_model.output_name == BINARY_OUTPUT)
+}
+
+pub async fn openapi_http() -> Response<Either<body::Body, body::BodySt>> {
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Either::Left(body::bytes(document().await.to_string())))
+        .unwrap()
+}
+
+/// 由路由表, income_param!, api_model! 及 biz result 生成 OpenAPI 3.1 文档
+pub async fn document() -> Value {
+    let routes = ROUTER.read().await.routes().to_vec();
+    let income_params = INCOME_PARAM_MAP.read().await.clone();
+    let api_models = API_MODELS.read().await.clone();
+    let skip_auth_ifs = SKIP_AUTH_IFS.read().await.clone();
+    let biz_results = BIZ_RESULT_MAP.read().await.clone();
+
+    let error_responses = error_responses(&biz_results);
+
+    let mut schemas = Map::new();
+    schemas.insert(ERROR_RES_SCHEMA.to_string(), error_res_schema());
+
+    let mut paths = Map::new();
+    for route in routes {
+        let uri = route.uri();
+        if route.is_legacy() {
+            debug!("openapi: skip raw regex route {}", uri.name());
+            continue;
+        }
+
+        let (path, path_patterns) = match router::template_path(uri.path()) {
+            Ok(template) => template,
+            Err(err) => {
+                warn!("openapi: skip route {}: {}", uri.name(), err);
+                continue;
+            }
+        };
+
+        let api_model = api_models.get(uri.name());
+        if let Some(api_model) = api_model {
+            schemas.insert(api_model.input_name.to_string(), api_model.input_schema.clone());
+            schemas.insert(api_model.output_name.to_string(), api_model.output_schema.clone());
+        }
+
+        let mut operation = json!({
+            "operationId": uri.name(),
+            "summary": uri.name(),
+            "x-action": uri.action().to_string(),
+            "parameters": parameters(&path_patterns, income_params.get(uri.name())),
+            "responses": responses(uri, api_model, &error_responses),
+        });
+        if let Some(request_body) = request_body(uri, api_model, income_params.get(uri.name())) {
+            operation["requestBody"] = request_body;
+        }
+        if !skip_auth_ifs.contains(&uri.name().to_string()) {
+            operation["security"] = Value::Array(AUTH_HEADERS.iter().map(|auth| json!({ auth.upper_case_value(): [] })).collect());
+        }
+
+        let item = paths.entry(path).or_insert_with(|| json!({}));
+        item[uri.method().as_str().to_lowercase()] = operation;
+    }
+
+    let mut security_schemes = Map::new();
+    for auth in AUTH_HEADERS.iter() {
+        security_schemes.insert(
+            auth.upper_case_value().to_string(),
+            json!({ "type": "apiKey", "in": "header", "name": auth.upper_case_value() }),
+        );
+    }
+
+    json!({
+        "openapi": OPENAPI_VERSION,
+        "info": info(),
+        "paths": paths,
+        "components": {
+            "schemas": schemas,
+            "responses": Map::from_iter(error_responses.into_iter().map(|(status, response)| (error_response_name(status), response))),
+            "securitySchemes": security_schemes,
+        },
+    })
+}
+
+fn info() -> Value {
+    let (title, version) = match env::var("FUNC_CONTEXT") {
+        Ok(_) => (FUNC_CONTEXT.name.clone(), FUNC_CONTEXT.version.clone()),
+        Err(_) => (None, None),
+    };
+    json!({
+        "title": title.unwrap_or("sg-function".to_string()),
+        "version": version.unwrap_or("0.0.0".to_string()),
+    })
+}
+
+fn param_type_schema(param_type: &ParamType) -> Value {
+    match param_type {
+        ParamType::Bool => json!({ "type": "boolean" }),
+        ParamType::String => json!({ "type": "string" }),
+        ParamType::Number => json!({ "type": "number" }),
+        ParamType::HashMap => json!({ "type": "object" }),
+        ParamType::Vec => json!({ "type": "array" }),
+    }
+}
+
+/// path 参数取自路由模板, 类型取自 income_param!; 以数字指定位置的 path 参数已由模板覆盖
+fn parameters(path_patterns: &[(String, String)], income_params: Option<&ExtraParamMap>) -> Value {
+    let income_params = income_params.map(|m| m.params.values().collect::<Vec<_>>()).unwrap_or_default();
+
+    let mut parameters = Vec::<Value>::new();
+    for (name, pattern) in path_patterns {
+        let mut schema = income_params
+            .iter()
+            .find(|def| def.from == ParamFrom::Path && &def.name == name)
+            .map(|def| param_type_schema(&def.param_type))
+            .unwrap_or(json!({ "type": "string" }));
+        if pattern != DEFAULT_PARAM_PATTERN {
+            schema["type"] = json!("string");
+            schema["pattern"] = json!(format!("^{}$", pattern));
+        }
+        parameters.push(json!({ "name": name, "in": "path", "required": true, "schema": schema }));
+    }
+
+    let mut others = income_params
+        .iter()
+        .filter_map(|def| {
+            let location = match def.from {
+                ParamFrom::Header => "header",
+                ParamFrom::Query => "query",
+                _ => return None,
+            };
+            Some(json!({
+                "name": def.name,
+                "in": location,
+                "required": def.required,
+                "schema": param_type_schema(&def.param_type),
+            }))
+        })
+        .collect::<Vec<Value>>();
+    others.sort_by_key(|parameter| (parameter["in"].to_string(), parameter["name"].to_string()));
+    parameters.extend(others);
+
+    Value::Array(parameters)
+}
+
+fn schema_ref(name: &str) -> Value {
+    json!({ "$ref": format!("#/components/schemas/{}", name) })
+}
+
+fn request_body(uri: &URI, api_model: Option<&ApiModel>, income_params: Option<&ExtraParamMap>) -> Option<Value> {
+    if [Method::GET, Method::HEAD, Method::DELETE, Method::OPTIONS].contains(uri.method()) {
+        return None;
+    }
+
+    let income_params = income_params.map(|m| m.params.values().collect::<Vec<_>>()).unwrap_or_default();
+    let mut content = Map::new();
+
+    if let Some(api_model) = api_model {
+        let mut required = income_params
+            .iter()
+            .filter(|def| def.from == ParamFrom::Body && def.required)
+            .map(|def| def.name.clone())
+            .collect::<Vec<String>>();
+        required.sort();
+
+        let mut schema = schema_ref(api_model.input_name);
+        if !required.is_empty() {
+            schema = json!({ "allOf": [schema, { "required": required }] });
+        }
+        if *uri.bulk_input() {
+            schema = json!({ "type": "array", "items": schema });
+        }
+        content.insert("application/json".to_string(), json!({ "schema": schema }));
+    }
+
+    let form_data = income_params.iter().filter(|def| def.from == ParamFrom::FormData).collect::<Vec<_>>();
+    if !form_data.is_empty() {
+        let properties = Map::from_iter(form_data.iter().map(|def| (def.name.clone(), param_type_schema(&def.param_type))));
+        let mut required = form_data.iter().filter(|def| def.required).map(|def| def.name.clone()).collect::<Vec<String>>();
+        required.sort();
+        content.insert(
+            "multipart/form-data".to_string(),
+            json!({ "schema": { "type": "object", "properties": properties, "required": required } }),
+        );
+    }
+
+    if content.is_empty() {
+        return None;
+    }
+    Some(json!({ "required": true, "content": content }))
+}
+
+/// 成功响应为 Res<IfRes<T>>, 错误响应按 status code 引用 components.responses
+fn responses(uri: &URI, api_model: Option<&ApiModel>, error_responses: &BTreeMap<u16, Value>) -> Value {
+    let mut responses = Map::new();
+
+    let success = match api_model {
+        Some(api_model) if api_model.output_name == BINARY_OUTPUT => json!({
+            "description": "OK",
+            "content": { "application/octet-stream": { "schema": { "type": "string", "contentMediaType": "application/octet-stream" } } },
+        }),
+        _ => {
+            let output = api_model.map(|m| schema_ref(m.output_name)).unwrap_or(json!({ "type": "object" }));
+            json!({
+                "description": "OK",
+                "content": { "application/json": { "schema": res_schema(if_res_schema(uri, output)) } },
+            })
+        }
+    };
+    responses.insert(StatusCode::OK.as_u16().to_string(), success);
+
+    for status in error_responses.keys() {
+        responses.insert(
+            status.to_string(),
+            json!({ "$ref": format!("#/components/responses/{}", error_response_name(*status)) }),
+        );
+    }
+
+    Value::Object(responses)
+}
+
+fn res_schema(result: Value) -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "code": { "type": "integer", "format": "int32" },
+            "message": { "type": "string" },
+            "result": result,
+        },
+        "required": ["code", "message"],
+    })
+}
+
+fn if_res_schema(uri: &URI, output: Value) -> Value {
+    let mut properties = json!({
+        "saga_id": { "type": "string" },
+        "uri_name": { "type": "string" },
+        "action": { "type": "string" },
+        "bulk_output": { "type": "boolean" },
+    });
+    if *uri.bulk_output() {
+        properties["outputs"] = json!({ "type": "array", "items": output });
+        properties["page_info"] = json!({
+            "type": "object",
+            "properties": {
+                "total_data": { "type": "integer", "format": "int32" },
+                "current_page_no": { "type": "integer", "format": "int32" },
+                "total_pages": { "type": "integer", "format": "int32" },
+                "page_size": { "type": "integer", "format": "int32" },
+            },
+        });
+    } else {
+        properties["output"] = output;
+    }
+    json!({ "type": "object", "properties": properties })
+}
+
+fn error_res_schema() -> Value {
+    res_schema(json!({ "type": "null" }))
+}
+
+fn error_response_name(status: u16) -> String {
+    format!("Error{}", status)
+}
+
+/// 按 status code 归类 biz result, 每个 biz code 作为一个 example
+fn error_responses(biz_results: &HashMap<String, BizResult<'static>>) -> BTreeMap<u16, Value> {
+    let mut grouped = BTreeMap::<u16, Vec<&BizResult<'static>>>::new();
+    for biz_result in biz_results.values() {
+        if (200..300).contains(&biz_result.status_code()) {
+            continue;
+        }
+        grouped.entry(biz_result.status_code()).or_default().push(biz_result);
+    }
+
+    grouped
+        .into_iter()
+        .map(|(status, mut results)| {
+            results.sort_by_key(|biz_result| biz_result.biz_code());
+            let examples = Map::from_iter(results.iter().map(|biz_result| {
+                (
+                    biz_result.name(),
+                    json!({
+                        "summary": format!("{} {}", biz_result.biz_code(), biz_result.message()),
+                        "value": { "code": biz_result.biz_code(), "message": biz_result.message(), "result": null },
+                    }),
+                )
+            }));
+            let description = StatusCode::from_u16(status)
+                .ok()
+                .and_then(|status| status.canonical_reason())
+                .unwrap_or("Error")
+                .to_string();
+            (
+                status,
+                json!({
+                    "description": description,
+                    "content": { "application/json": { "schema": schema_ref(ERROR_RES_SCHEMA), "examples": examples } },
+                }),
+            )
+        })
+        .collect()
+}
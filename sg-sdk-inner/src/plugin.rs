@@ -0,0 +1,100 @@
+use std::{collections::HashMap, env, sync::Arc};
+
+use bevy_reflect::Reflect;
+use tracing::info;
+
+use crate::{
+    model::{IfRes, Params},
+    traits::{ModelTrait, Plugin},
+    HttpResult, FUNC_CONTEXT, FUNC_CONTEXT_V1BETA2, PLUGINS,
+};
+
+pub async fn insert_plugin(plugin: Arc<dyn Plugin>) -> HttpResult<()> {
+    info!("set plugin: {}", plugin.name());
+    let mut plugins = PLUGINS.write().await;
+    match plugins.insert(plugin.name().to_string(), plugin.clone()) {
+        None => {}
+        Some(_) => {
+            return Err(format!("plugin is exist: {}", plugin.name()).into());
+        }
+    };
+    Ok(())
+}
+
+pub fn pre_plugin_names() -> Vec<String> {
+    if env::var("FUNC_CONTEXT").is_ok() {
+        if let Some(names) = &FUNC_CONTEXT.prePlugins {
+            return names.clone();
+        }
+    }
+    if env::var("FUNC_CONTEXT_V1BETA2").is_ok() {
+        if let Some(names) = &FUNC_CONTEXT_V1BETA2.preHooks {
+            return names.clone();
+        }
+    }
+    vec![]
+}
+
+pub fn post_plugin_names() -> Vec<String> {
+    if env::var("FUNC_CONTEXT").is_ok() {
+        if let Some(names) = &FUNC_CONTEXT.postPlugins {
+            return names.clone();
+        }
+    }
+    if env::var("FUNC_CONTEXT_V1BETA2").is_ok() {
+        if let Some(names) = &FUNC_CONTEXT_V1BETA2.postHooks {
+            return names.clone();
+        }
+    }
+    vec![]
+}
+
+/// 启动时检查 FUNC_CONTEXT 中声明的插件是否都已注册
+pub async fn check_plugins() -> HttpResult<()> {
+    let plugins = PLUGINS.read().await;
+
+    let missing: Vec<String> = pre_plugin_names()
+        .into_iter()
+        .chain(post_plugin_names().into_iter())
+        .filter(|name| !plugins.contains_key(name))
+        .collect();
+
+    if !missing.is_empty() {
+        return Err(format!("plugins declared in function context but not registered: {}", missing.join(",")).into());
+    }
+
+    Ok(())
+}
+
+async fn find_plugins(names: Vec<String>) -> HttpResult<Vec<Arc<dyn Plugin>>> {
+    let plugins = PLUGINS.read().await;
+
+    let mut found = Vec::<Arc<dyn Plugin>>::new();
+    for name in names {
+        found.push(plugins.get(&name).ok_or(format!("plugin not registered: {}", name))?.clone());
+    }
+    Ok(found)
+}
+
+pub async fn run_pre_plugins(params: &mut Params) -> HttpResult<()> {
+    for plugin in find_plugins(pre_plugin_names()).await? {
+        plugin.exec_pre_hook(params).await?;
+    }
+    Ok(())
+}
+
+pub async fn run_post_plugins<T: ModelTrait + prost::Message + Default>(
+    params: &Params,
+    mut http_res: HttpResult<(IfRes<T>, HashMap<String, String>)>,
+) -> HttpResult<(IfRes<T>, HashMap<String, String>)>
+where
+    IfRes<T>: Reflect,
+{
+    for plugin in find_plugins(post_plugin_names()).await? {
+        match &mut http_res {
+            Ok((if_res, _)) => plugin.exec_post_hook(params, Some(if_res as &mut dyn Reflect)).await?,
+            Err(_) => plugin.exec_post_hook(params, None).await?,
+        }
+    }
+    http_res
+}
@@ -0,0 +1,184 @@
+use http_body_util::Either;
+use hyper::{header, Response, StatusCode};
+use tracing::{error, info};
+
+use crate::{body, native_grpc::SERVICE_NAME, HttpResult, API_MODELS, PROTO_TYPES, ROUTER};
+
+pub const PROTO_PATH: &str = "/sg.function.proto";
+
+/// dapr invoke 返回的信封, output/outputs 为 Any, outputs 中是 google.protobuf.ListValue
+const ENVELOPE: &str = r#"enum Action {
+  ACTION_UNSPECIFIED = 0;
+  ACTION_QUERY = 1;
+  ACTION_UPDATE = 2;
+  ACTION_DELETE = 3;
+  ACTION_INSERT = 4;
+  ACTION_TX = 5;
+  ACTION_FUNCTION = 6;
+}
+
+message IfResMessage {
+  optional string saga_id = 1;
+  optional string uri_name = 2;
+  optional Action action = 3;
+  bool bulk_output = 4;
+  optional google.protobuf.Any output = 5;
+  optional google.protobuf.Any outputs = 6;
+  optional PageInfoMessage page_info = 7;
+}
+
+message PageInfoMessage {
+  uint32 total_data = 1;
+  uint32 current_page_no = 2;
+  uint32 total_pages = 3;
+  uint32 page_size = 4;
+}
+"#;
+
+const WELL_KNOWN_TYPES: [(&str, &str); 6] = [
+    ("google.protobuf.Any", "google/protobuf/any.proto"),
+    ("google.protobuf.ListValue", "google/protobuf/struct.proto"),
+    ("google.protobuf.Struct", "google/protobuf/struct.proto"),
+    ("google.protobuf.Value", "google/protobuf/struct.proto"),
+    ("google.protobuf.Timestamp", "google/protobuf/timestamp.proto"),
+    ("google.protobuf.Duration", "google/protobuf/duration.proto"),
+];
+
+const SCALAR_TYPES: [&str; 15] = [
+    "double", "float", "int32", "int64", "uint32", "uint64", "sint32", "sint64", "fixed32", "fixed64", "sfixed32", "sfixed64", "bool", "string", "bytes",
+];
+
+/// 登记 message 或 enum 的定义, 同名的以后登记的为准
+pub async fn insert_proto_type(name: &str, definition: &'static str) -> HttpResult<()> {
+    if definition.is_empty() {
+        return Err(format!("proto definition of '{}' is empty, derive Model or EnumGenerate first", name).into());
+    }
+    info!("set proto type: {}", name);
+    let mut proto_types = PROTO_TYPES.write().await;
+    proto_types.insert(name.to_string(), definition);
+    Ok(())
+}
+
+pub async fn proto_http() -> Response<Either<body::Body, body::BodySt>> {
+    Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "text/plain; charset=utf-8")
+        .body(Either::Left(body::bytes(document().await)))
+        .unwrap()
+}
+
+/// 写出 .proto 文件, 供构建脚本或 CI 生成多语言客户端
+pub async fn write_proto(path: &str) -> HttpResult<()> {
+    check_types().await?;
+    tokio::fs::write(path, document().await).await?;
+    info!("proto file written: {}", path);
+    Ok(())
+}
+
+/// message 字段引用的类型都需要登记定义, 嵌套的 message 与 enum 需要通过 proto_types! 登记, 否则生成的 .proto 无法编译
+pub async fn check_types() -> HttpResult<()> {
+    let proto_types = PROTO_TYPES.read().await.clone();
+    let mut names = proto_types.keys().collect::<Vec<&String>>();
+    names.sort();
+
+    let mut errors = Vec::<String>::new();
+    for name in names {
+        for referenced in referenced_types(proto_types[name]) {
+            let defined = SCALAR_TYPES.contains(&referenced.as_str())
+                || WELL_KNOWN_TYPES.iter().any(|(well_known, _)| *well_known == referenced)
+                || proto_types.contains_key(&referenced);
+            if !defined {
+                errors.push(format!("proto type {}: referenced type '{}' not registered in proto_types!", name, referenced));
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        return Ok(());
+    }
+    for err in errors.iter() {
+        error!("proto check: {}", err);
+    }
+    Err(format!("proto check failed:\n{}", errors.join("\n")).into())
+}
+
+/// message 定义中各字段的类型, 形如 `[optional|repeated] Type name = 1;` 或 `map<K, V> name = 1;`
+fn referenced_types(definition: &str) -> Vec<String> {
+    if !definition.starts_with("message ") {
+        return vec![];
+    }
+    definition
+        .lines()
+        .filter_map(|line| line.split_once(" = ").map(|(field, _)| field.trim()))
+        .filter_map(|field| match field.strip_prefix("map<") {
+            Some(map) => map
+                .split_once(">")
+                .and_then(|(key_value, _)| key_value.split_once(","))
+                .map(|(_, value)| value.trim()),
+            None => {
+                let tokens = field.split_whitespace().collect::<Vec<&str>>();
+                tokens.len().checked_sub(2).map(|i| tokens[i])
+            }
+        })
+        .map(|name| name.to_string())
+        .collect()
+}
+
+/// 原生 gRPC 服务的方法取自路由表, 输入输出取自 api_model!; bulk 的输入输出为 google.protobuf.ListValue
+pub async fn document() -> String {
+    let routes = ROUTER.read().await.routes().to_vec();
+    let api_models = API_MODELS.read().await.clone();
+    let mut proto_types = PROTO_TYPES
+        .read()
+        .await
+        .iter()
+        .map(|(name, definition)| (name.clone(), *definition))
+        .collect::<Vec<_>>();
+    proto_types.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let (package, service) = SERVICE_NAME.rsplit_once(".").unwrap_or(("sg.function", SERVICE_NAME));
+
+    let mut uris = routes.iter().map(|route| route.uri().clone()).collect::<Vec<_>>();
+    uris.sort_by(|a, b| a.name().cmp(b.name()));
+
+    let mut rpcs = Vec::<String>::new();
+    for uri in uris {
+        let Some(api_model) = api_models.get(uri.name()) else {
+            rpcs.push(format!("  // {}: api_model! not registered", uri.name()));
+            continue;
+        };
+        let input = if *uri.bulk_input() {
+            "google.protobuf.ListValue"
+        } else {
+            api_model.input_name
+        };
+        let output = if *uri.bulk_output() {
+            "google.protobuf.ListValue"
+        } else {
+            api_model.output_name
+        };
+        rpcs.push(format!("  rpc {} ({}) returns ({});", uri.name(), input, output));
+    }
+
+    let mut body = String::new();
+    body.push_str(&format!("service {} {{\n{}\n}}\n\n", service, rpcs.join("\n")));
+    body.push_str(ENVELOPE);
+    for (_, definition) in proto_types.iter() {
+        body.push('\n');
+        body.push_str(definition);
+    }
+
+    let mut imports = WELL_KNOWN_TYPES
+        .iter()
+        .filter(|(name, _)| body.contains(name))
+        .map(|(_, file)| format!("import \"{}\";", file))
+        .collect::<Vec<String>>();
+    imports.dedup();
+
+    format!(
+        "// generated by sg-sdk, do not edit\nsyntax = \"proto3\";\n\npackage {};\n\n{}\n\n{}",
+        package,
+        imports.join("\n"),
+        body
+    )
+}
@@ -0,0 +1,282 @@
+use std::{collections::HashMap, future::Future, pin::Pin};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use dapr::{
+    appcallback::{ListTopicSubscriptionsResponse, TopicEventRequest, TopicEventResponse},
+    dapr::dapr::proto::runtime::v1::{topic_event_response::TopicEventResponseStatus, TopicRoutes, TopicSubscription},
+};
+use http_body_util::Either;
+use hyper::{header, Request, Response};
+use serde_json::{json, Value};
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    body,
+    model::{Action, IfInfo, Params},
+    util::{collect_body, find_response_error, utc_timestamp, BizResult, ResponseError},
+    HttpResult, TOPICS,
+};
+
+pub const DAPR_SUBSCRIBE_PATH: &str = "/dapr/subscribe";
+
+/// pubsub name, topic, route, name
+#[derive(PartialEq, Eq, Debug, Hash, Clone, Default)]
+pub struct Topic(pub &'static str, pub &'static str, pub &'static str, pub &'static str);
+
+impl Topic {
+    pub fn pubsub_name(&self) -> &str {
+        &self.0
+    }
+
+    pub fn topic(&self) -> &str {
+        &self.1
+    }
+
+    pub fn route(&self) -> &str {
+        &self.2
+    }
+
+    pub fn name(&self) -> &str {
+        &self.3
+    }
+}
+
+pub type TopicHandler = fn(Params) -> Pin<Box<dyn Future<Output = HttpResult<()>> + Send>>;
+
+pub async fn insert_topic(topic: Topic, handler: TopicHandler) -> HttpResult<()> {
+    info!("set topic: {:?}", topic);
+    let mut topics = TOPICS.write().await;
+
+    if topics
+        .values()
+        .any(|(t, _)| t.pubsub_name() == topic.pubsub_name() && t.topic() == topic.topic())
+    {
+        return Err(Box::new(ResponseError {
+            biz_res: format!("topic is subscribed: {}.{}", topic.pubsub_name(), topic.topic()),
+            message: None,
+        }));
+    }
+
+    match topics.insert(topic.name().to_string(), (topic.clone(), handler)) {
+        None => {}
+        Some(_) => {
+            return Err(Box::new(ResponseError {
+                biz_res: format!("topic is exist: {}", topic.name()),
+                message: None,
+            }));
+        }
+    };
+
+    Ok(())
+}
+
+pub async fn find_topic(pubsub_name: &str, topic: &str) -> Option<(Topic, TopicHandler)> {
+    let topics = TOPICS.read().await;
+    topics
+        .values()
+        .find(|(t, _)| t.pubsub_name() == pubsub_name && t.topic() == topic)
+        .map(|(t, h)| (t.clone(), *h))
+}
+
+pub async fn find_topic_by_route(route: &str) -> Option<(Topic, TopicHandler)> {
+    let topics = TOPICS.read().await;
+    topics.values().find(|(t, _)| t.route() == route).map(|(t, h)| (t.clone(), *h))
+}
+
+pub async fn list_topic_subscriptions() -> ListTopicSubscriptionsResponse {
+    let topics = TOPICS.read().await;
+
+    let subscriptions = topics
+        .values()
+        .map(|(t, _)| TopicSubscription {
+            pubsub_name: t.pubsub_name().to_string(),
+            topic: t.topic().to_string(),
+            routes: Some(TopicRoutes {
+                default: t.route().to_string(),
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+        .collect();
+
+    ListTopicSubscriptionsResponse { subscriptions }
+}
+
+pub async fn list_topic_subscriptions_http() -> Response<Either<body::Body, body::BodySt>> {
+    let topics = TOPICS.read().await;
+
+    let subscriptions: Vec<Value> = topics
+        .values()
+        .map(|(t, _)| {
+            json!({
+                "pubsubname": t.pubsub_name(),
+                "topic": t.topic(),
+                "route": t.route(),
+            })
+        })
+        .collect();
+
+    debug!("dapr subscriptions: {:?}", subscriptions);
+
+    json_resp(Value::Array(subscriptions).to_string())
+}
+
+pub async fn on_topic_event_grpc(event: TopicEventRequest) -> TopicEventResponse {
+    let Some((topic, handler)) = find_topic(&event.pubsub_name, &event.topic).await else {
+        warn!("topic event of '{}.{}' has no handler, drop it", event.pubsub_name, event.topic);
+        return TopicEventResponse {
+            status: TopicEventResponseStatus::Drop as i32,
+        };
+    };
+
+    let mut headers = HashMap::<String, String>::new();
+    headers.insert("content-type".to_string(), event.data_content_type.clone());
+    headers.insert("ce-id".to_string(), event.id.clone());
+    headers.insert("ce-source".to_string(), event.source.clone());
+    headers.insert("ce-type".to_string(), event.r#type.clone());
+    headers.insert("ce-specversion".to_string(), event.spec_version.clone());
+    headers.insert("ce-pubsubname".to_string(), event.pubsub_name.clone());
+    headers.insert("ce-topic".to_string(), event.topic.clone());
+
+    let params = topic_params(&topic, headers, event.data);
+
+    TopicEventResponse {
+        status: dispatch(handler, params).await as i32,
+    }
+}
+
+pub async fn on_topic_event_http(topic: Topic, handler: TopicHandler, req: Request<body::ReqBody>) -> Response<Either<body::Body, body::BodySt>> {
+    let content_type = req
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("application/json")
+        .to_string();
+
+    let body_bytes = match collect_body(req.into_body()).await {
+        Ok(body_bytes) => body_bytes,
+        Err(err) => {
+            error!("read topic event body error: {}", err);
+            return topic_status_resp(topic_event_status(&Err(err)).await);
+        }
+    };
+
+    let mut headers = HashMap::<String, String>::new();
+
+    let data = if content_type.starts_with("application/cloudevents") {
+        let envelope = match serde_json::from_slice::<Value>(&body_bytes) {
+            Ok(envelope) => envelope,
+            Err(err) => {
+                error!("topic event is not a valid cloud event, drop it: {}", err);
+                return topic_status_resp(TopicEventResponseStatus::Drop);
+            }
+        };
+
+        for (attr, key) in [
+            ("id", "ce-id"),
+            ("source", "ce-source"),
+            ("type", "ce-type"),
+            ("specversion", "ce-specversion"),
+            ("pubsubname", "ce-pubsubname"),
+            ("topic", "ce-topic"),
+            ("saga_id", "saga_id"),
+        ] {
+            if let Some(v) = envelope.get(attr).and_then(|v| v.as_str()) {
+                headers.insert(key.to_string(), v.to_string());
+            }
+        }
+
+        let data_content_type = envelope
+            .get("datacontenttype")
+            .and_then(|v| v.as_str())
+            .unwrap_or("application/json")
+            .to_string();
+        headers.insert("content-type".to_string(), data_content_type);
+
+        if let Some(data_base64) = envelope.get("data_base64").and_then(|v| v.as_str()) {
+            match STANDARD.decode(data_base64) {
+                Ok(data) => data,
+                Err(err) => {
+                    error!("topic event data_base64 decode error, drop it: {}", err);
+                    return topic_status_resp(TopicEventResponseStatus::Drop);
+                }
+            }
+        } else {
+            match envelope.get("data") {
+                None | Some(Value::Null) => vec![],
+                Some(Value::String(s)) => s.as_bytes().to_vec(),
+                Some(v) => v.to_string().into_bytes(),
+            }
+        }
+    } else {
+        headers.insert("content-type".to_string(), content_type);
+        body_bytes
+    };
+
+    let params = topic_params(&topic, headers, data);
+
+    topic_status_resp(dispatch(handler, params).await)
+}
+
+fn topic_params(topic: &Topic, header: HashMap<String, String>, data: Vec<u8>) -> Params {
+    let mut params: Params = Default::default();
+
+    params.uri = topic.name().to_string();
+    params.header = header;
+    params.if_info = IfInfo {
+        action: Action::Function,
+        bulk_input: false,
+        bulk_output: false,
+    };
+    params.body = if data.is_empty() { None } else { Some(data) };
+
+    params
+}
+
+async fn dispatch(handler: TopicHandler, params: Params) -> TopicEventResponseStatus {
+    info!(start_time = %utc_timestamp(), params = ?params, "accept topic event");
+
+    let topic_name = params.uri.clone();
+    let res = handler(params).await;
+    let status = topic_event_status(&res).await;
+
+    match res {
+        Ok(_) => info!(topic = %topic_name, status = ?status, "topic event handled"),
+        Err(err) => error!(topic = %topic_name, status = ?status, error = ?err, "topic event handled with error"),
+    }
+
+    status
+}
+
+/// Ok 为 SUCCESS, 4xx 的业务错误不会因重试而成功, 为 DROP, 其余为 RETRY
+pub async fn topic_event_status(res: &HttpResult<()>) -> TopicEventResponseStatus {
+    let Err(err) = res else {
+        return TopicEventResponseStatus::Success;
+    };
+
+    let Some(response_err) = find_response_error(err.as_ref()) else {
+        return TopicEventResponseStatus::Retry;
+    };
+
+    match BizResult::from(response_err.biz_res.clone()).await {
+        Ok(biz_res) if (400..500).contains(&biz_res.status_code()) => TopicEventResponseStatus::Drop,
+        _ => TopicEventResponseStatus::Retry,
+    }
+}
+
+fn topic_status_resp(status: TopicEventResponseStatus) -> Response<Either<body::Body, body::BodySt>> {
+    let status = match status {
+        TopicEventResponseStatus::Success => "SUCCESS",
+        TopicEventResponseStatus::Retry => "RETRY",
+        TopicEventResponseStatus::Drop => "DROP",
+    };
+
+    json_resp(json!({ "status": status }).to_string())
+}
+
+fn json_resp(json: String) -> Response<Either<body::Body, body::BodySt>> {
+    Response::builder()
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Either::Left(body::bytes(json)))
+        .unwrap()
+}
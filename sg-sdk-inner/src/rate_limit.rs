@@ -0,0 +1,349 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use base64::{engine::general_purpose, Engine};
+use dapr::dapr::dapr::proto::{
+    common::v1::{state_options::StateConcurrency, Etag, StateItem, StateOptions},
+    runtime::v1::{GetStateRequest, SaveStateRequest},
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tonic::Code;
+use tracing::{info, warn};
+
+use crate::{
+    config::get_dapr_grpc_client,
+    inner_biz_result::*,
+    model::{AuthHeader, Params},
+    util::{err_boxed_full, ResponseError, URI},
+    HttpResult, RATE_LIMITS, RATE_LIMIT_SEMAPHORES, RATE_LIMIT_STORE, RATE_LIMIT_TRUSTED_PROXIES,
+};
+
+/// 内存中最多保留的令牌桶数量, 超过时清理已回满的桶
+const MAX_MEMORY_BUCKETS: usize = 10000;
+/// dapr state 并发写冲突时的重试次数
+const DAPR_STORE_RETRIES: usize = 3;
+/// 最多保留的并发信号量数量, 超过时清理没有请求占用的信号量
+const MAX_SEMAPHORES: usize = 10000;
+
+/// 限流的维度
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitKey {
+    /// 整个 URI 共用一个限额
+    Uri,
+    /// JWT claims 中的 sub
+    JwtSubject,
+    ApiKey,
+    /// 连接的对端地址; 对端为受信代理时, 取 X-Forwarded-For 中最右侧的非受信地址, 其次为 X-Real-IP
+    RemoteAddr,
+}
+
+impl RateLimitKey {
+    fn identity(&self, params: &Params, trusted_proxies: &[IpAddr]) -> String {
+        let header = |name: &str| params.header.iter().find(|(k, _)| k.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_str());
+
+        let identity = match self {
+            RateLimitKey::Uri => Some("*".to_string()),
+            RateLimitKey::JwtSubject => header(AuthHeader::XSGAuthJWT.lower_case_value()).and_then(jwt_subject),
+            RateLimitKey::ApiKey => header(AuthHeader::XSGAuthApiKey.lower_case_value()).map(|api_key| {
+                // 不将 api key 原文写入 state store, 摘要需在不同版本间保持稳定
+                hex::encode(Sha256::digest(api_key.as_bytes()))
+            }),
+            RateLimitKey::RemoteAddr => params.remote_addr.map(|peer| {
+                if !trusted_proxies.contains(&peer) {
+                    return peer.to_string();
+                }
+                let forwarded = header("x-forwarded-for").map(forwarded_for).unwrap_or_default();
+                match forwarded.iter().rev().find(|addr| !trusted_proxies.contains(*addr)) {
+                    Some(addr) => addr.to_string(),
+                    None => forwarded
+                        .first()
+                        .copied()
+                        .or(header("x-real-ip").and_then(|v| v.trim().parse::<IpAddr>().ok()))
+                        .unwrap_or(peer)
+                        .to_string(),
+                }
+            }),
+        };
+
+        identity.filter(|v| !v.is_empty()).unwrap_or("anonymous".to_string())
+    }
+}
+
+/// 解析失败的地址视为客户端伪造, 不参与判断
+fn forwarded_for(value: &str) -> Vec<IpAddr> {
+    value.split(",").filter_map(|v| v.trim().parse::<IpAddr>().ok()).collect()
+}
+
+pub(crate) fn jwt_subject(token: &str) -> Option<String> {
+    let token = token.trim_start_matches("Bearer ").trim();
+    let claims = token.split(".").nth(1)?;
+    let decoded = general_purpose::URL_SAFE_NO_PAD
+        .decode(claims)
+        .or_else(|_| general_purpose::STANDARD_NO_PAD.decode(claims))
+        .ok()?;
+    let claims = serde_json::from_slice::<serde_json::Value>(&decoded).ok()?;
+    match claims.get("sub")? {
+        serde_json::Value::String(sub) => Some(sub.to_string()),
+        sub => Some(sub.to_string()),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RateLimit {
+    pub uri: URI,
+    /// 每秒补充的令牌数, 为 0 时不限制速率
+    pub rate: f64,
+    /// 令牌桶容量, 即允许的突发请求数
+    pub burst: u32,
+    /// 单实例最大并发数, 为 0 时不限制
+    pub max_in_flight: usize,
+    pub key: RateLimitKey,
+}
+
+impl RateLimit {
+    pub fn new(uri: URI, rate: f64, burst: u32, max_in_flight: usize, key: RateLimitKey) -> Self {
+        RateLimit {
+            uri,
+            rate,
+            burst,
+            max_in_flight,
+            key,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+struct TokenBucket {
+    tokens: f64,
+    updated_ms: u128,
+    /// 最近一次取令牌时的限流参数, 淘汰时以各自的参数补充
+    #[serde(default)]
+    rate: f64,
+    #[serde(default)]
+    burst: u32,
+}
+
+impl TokenBucket {
+    fn full(rate: f64, burst: u32, now_ms: u128) -> Self {
+        TokenBucket {
+            tokens: burst as f64,
+            updated_ms: now_ms,
+            rate,
+            burst,
+        }
+    }
+
+    fn refill(&mut self, now_ms: u128) {
+        let elapsed = now_ms.saturating_sub(self.updated_ms) as f64 / 1000.0;
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.burst as f64);
+        self.updated_ms = now_ms;
+    }
+
+    fn refill_with(&mut self, rate: f64, burst: u32, now_ms: u128) {
+        self.rate = rate;
+        self.burst = burst;
+        self.refill(now_ms);
+    }
+
+    fn try_take(&mut self) -> bool {
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+fn now_ms() -> u128 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis()).unwrap_or_default()
+}
+
+/// 令牌桶的存储, 默认为进程内存, 多实例共享限额时使用 DaprRateLimitStore
+#[async_trait::async_trait]
+pub trait RateLimitStore: Send + Sync {
+    /// 从 key 对应的令牌桶取出一个令牌, 没有可用令牌时返回 false; 存储读写失败时返回 Err, 请求放行
+    async fn try_acquire(&self, key: &str, rate: f64, burst: u32) -> HttpResult<bool>;
+}
+
+#[derive(Debug, Default)]
+pub struct MemoryRateLimitStore {
+    buckets: Mutex<HashMap<String, TokenBucket>>,
+}
+
+#[async_trait::async_trait]
+impl RateLimitStore for MemoryRateLimitStore {
+    async fn try_acquire(&self, key: &str, rate: f64, burst: u32) -> HttpResult<bool> {
+        let now_ms = now_ms();
+        let mut buckets = self.buckets.lock().map_err(|err| err.to_string())?;
+
+        if buckets.len() >= MAX_MEMORY_BUCKETS && !buckets.contains_key(key) {
+            buckets.retain(|_, bucket| {
+                bucket.refill(now_ms);
+                bucket.tokens < bucket.burst as f64
+            });
+        }
+
+        let bucket = buckets.entry(key.to_string()).or_insert(TokenBucket::full(rate, burst, now_ms));
+        bucket.refill_with(rate, burst, now_ms);
+        Ok(bucket.try_take())
+    }
+}
+
+/// 令牌桶保存在 dapr state store 中, 以 etag 做乐观并发控制, 实现集群范围的限额
+#[derive(Debug, Clone)]
+pub struct DaprRateLimitStore {
+    store_name: String,
+}
+
+impl DaprRateLimitStore {
+    pub fn new(store_name: &str) -> Self {
+        DaprRateLimitStore {
+            store_name: store_name.to_string(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl RateLimitStore for DaprRateLimitStore {
+    async fn try_acquire(&self, key: &str, rate: f64, burst: u32) -> HttpResult<bool> {
+        let key = format!("rate-limit||{}", key);
+        let mut client = get_dapr_grpc_client().await?;
+
+        for _ in 0..DAPR_STORE_RETRIES {
+            let state = client
+                .get_state(GetStateRequest {
+                    store_name: self.store_name.clone(),
+                    key: key.clone(),
+                    ..Default::default()
+                })
+                .await?
+                .into_inner();
+
+            let now_ms = now_ms();
+            let mut bucket = match serde_json::from_slice::<TokenBucket>(&state.data) {
+                Ok(bucket) => bucket,
+                Err(_) => TokenBucket::full(rate, burst, now_ms),
+            };
+            bucket.refill_with(rate, burst, now_ms);
+            if !bucket.try_take() {
+                return Ok(false);
+            }
+
+            let saved = client
+                .save_state(SaveStateRequest {
+                    store_name: self.store_name.clone(),
+                    states: vec![StateItem {
+                        key: key.clone(),
+                        value: serde_json::to_vec(&bucket)?,
+                        etag: if state.etag.is_empty() { None } else { Some(Etag { value: state.etag }) },
+                        options: Some(StateOptions {
+                            concurrency: StateConcurrency::ConcurrencyFirstWrite as i32,
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    }],
+                })
+                .await;
+
+            match saved {
+                Ok(_) => return Ok(true),
+                // etag 不匹配时 dapr 返回 Aborted, 重新读取后重试
+                Err(status) if status.code() == Code::Aborted => {
+                    warn!("save rate limit state '{}' conflict: {}", key, status.message())
+                }
+                Err(status) => return Err(status.into()),
+            }
+        }
+
+        // 持续冲突说明该 key 的请求过于密集
+        Ok(false)
+    }
+}
+
+pub async fn insert_rate_limit(rate_limit: RateLimit) -> HttpResult<()> {
+    info!("set rate limit: {:?}", rate_limit);
+    let mut rate_limits = RATE_LIMITS.write().await;
+    match rate_limits.insert(rate_limit.uri.name().to_string(), rate_limit.clone()) {
+        None => {}
+        Some(_) => {
+            return Err(Box::new(ResponseError {
+                biz_res: format!("rate limit of uri '{}' is exist", rate_limit.uri.name()),
+                message: None,
+            }));
+        }
+    };
+    Ok(())
+}
+
+pub async fn set_rate_limit_store(store: Arc<dyn RateLimitStore>) -> HttpResult<()> {
+    let mut rate_limit_store = RATE_LIMIT_STORE.write().await;
+    *rate_limit_store = store;
+    Ok(())
+}
+
+/// RateLimitKey::RemoteAddr 只信任来自这些地址的 X-Forwarded-For / X-Real-IP, 经 dapr 调用时通常为 sidecar 的地址
+pub async fn set_trusted_proxies(proxies: Vec<IpAddr>) -> HttpResult<()> {
+    info!("set rate limit trusted proxies: {:?}", proxies);
+    let mut trusted_proxies = RATE_LIMIT_TRUSTED_PROXIES.write().await;
+    *trusted_proxies = proxies;
+    Ok(())
+}
+
+/// 鉴权之后执行, 超限时返回 TOO_MANY_REQUESTS; 返回的 permit 需持有到请求处理结束
+pub async fn check(params: &Params) -> HttpResult<Option<OwnedSemaphorePermit>> {
+    let rate_limit = match RATE_LIMITS.read().await.get(&params.uri) {
+        Some(rate_limit) => rate_limit.clone(),
+        None => return Ok(None),
+    };
+
+    let identity = rate_limit.key.identity(params, &RATE_LIMIT_TRUSTED_PROXIES.read().await);
+    let key = format!("{}:{}", params.uri, identity);
+
+    if rate_limit.rate > 0.0 {
+        let store = RATE_LIMIT_STORE.read().await.clone();
+        let acquired = match store.try_acquire(&key, rate_limit.rate, rate_limit.burst).await {
+            Ok(acquired) => acquired,
+            Err(err) => {
+                // 存储不可用时不阻断请求
+                warn!("rate limit store error, request allowed: {}", err);
+                true
+            }
+        };
+        if !acquired {
+            return Err(err_boxed_full(TOO_MANY_REQUESTS, "rate limit exceeded"));
+        }
+    }
+
+    if rate_limit.max_in_flight == 0 {
+        return Ok(None);
+    }
+
+    let semaphore = RATE_LIMIT_SEMAPHORES.read().await.get(&key).cloned();
+    let semaphore = match semaphore {
+        Some(semaphore) => semaphore,
+        None => {
+            // 只在新的 key 且达到上限时淘汰
+            let mut semaphores = RATE_LIMIT_SEMAPHORES.write().await;
+            if semaphores.len() >= MAX_SEMAPHORES && !semaphores.contains_key(&key) {
+                // permit 持有信号量的引用, 引用计数为 1 说明没有进行中的请求
+                semaphores.retain(|_, semaphore| Arc::strong_count(semaphore) > 1);
+            }
+            semaphores
+                .entry(key)
+                .or_insert_with(|| Arc::new(Semaphore::new(rate_limit.max_in_flight)))
+                .clone()
+        }
+    };
+
+    match semaphore.try_acquire_owned() {
+        Ok(permit) => Ok(Some(permit)),
+        Err(_) => Err(err_boxed_full(TOO_MANY_REQUESTS, "too many requests in flight")),
+    }
+}
@@ -0,0 +1,214 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    env,
+    fmt::{self, Debug},
+};
+
+use bevy_reflect::ReflectRef;
+use serde_json::Value;
+
+use crate::{traits::ModelTrait, REDACT_DENY_LIST};
+
+pub const MASK: &str = "******";
+
+const DEFAULT_HEADERS: [&str; 13] = [
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "set-cookie",
+    "x-sg-auth-jwt",
+    "x-sg-auth-basic",
+    "x-sg-auth-oauth2",
+    "x-sg-auth-aksk",
+    "x-sg-auth-apikey",
+    "x-sg-auth-digestauth",
+    "x-sg-auth-oidc",
+    "x-sg-auth-internal",
+    "dapr-api-token",
+];
+
+const DEFAULT_FIELDS: [&str; 5] = ["password", "passwd", "secret", "token", "api_key"];
+
+/// 需要在日志中打码的 header 与字段, 默认值之外可通过 LOG_REDACT_HEADERS / LOG_REDACT_FIELDS 追加, 逗号分隔
+#[derive(Debug, Clone, Default)]
+pub struct DenyList {
+    headers: HashSet<String>,
+    fields: HashSet<String>,
+}
+
+impl DenyList {
+    pub fn from_env() -> Self {
+        let from_env = |key: &str| -> Vec<String> {
+            env::var(key)
+                .unwrap_or_default()
+                .split(",")
+                .map(|v| v.trim().to_lowercase())
+                .filter(|v| !v.is_empty())
+                .collect()
+        };
+
+        DenyList {
+            headers: DEFAULT_HEADERS.iter().map(|h| h.to_string()).chain(from_env("LOG_REDACT_HEADERS")).collect(),
+            fields: DEFAULT_FIELDS.iter().map(|f| f.to_string()).chain(from_env("LOG_REDACT_FIELDS")).collect(),
+        }
+    }
+
+    pub fn is_denied_header(&self, name: &str) -> bool {
+        self.headers.contains(&name.to_lowercase())
+    }
+
+    pub fn is_denied_field(&self, name: &str) -> bool {
+        self.fields.contains(&name.to_lowercase())
+    }
+
+    /// query 的 key 可能为 `filter[token]`, 方括号中的部分也按字段检查
+    pub fn is_denied_param(&self, name: &str) -> bool {
+        name.split(['[', ']']).filter(|part| !part.is_empty()).any(|part| self.is_denied_field(part))
+    }
+}
+
+struct Masked;
+
+impl Debug for Masked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(MASK)
+    }
+}
+
+/// 打印 header 时将 deny-list 中的值打码
+pub struct RedactedHeaders<'a>(pub &'a HashMap<String, String>);
+
+impl<'a> Debug for RedactedHeaders<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let headers: BTreeMap<&str, &str> = self
+            .0
+            .iter()
+            .map(|(k, v)| (k.as_str(), if REDACT_DENY_LIST.is_denied_header(k) { MASK } else { v.as_str() }))
+            .collect();
+        headers.fmt(f)
+    }
+}
+
+/// 将 dapr metadata 中 deny-list 的字段打码, 用于打印前处理 clone 的请求
+pub fn mask_metadata(metadata: &mut HashMap<String, String>) {
+    for (k, v) in metadata.iter_mut() {
+        if REDACT_DENY_LIST.is_denied_field(k) {
+            *v = MASK.to_string();
+        }
+    }
+}
+
+/// 将 header 中 deny-list 的值打码, 用于打印前处理 clone 的请求
+pub fn mask_headers(headers: &mut HashMap<String, String>) {
+    for (k, v) in headers.iter_mut() {
+        if REDACT_DENY_LIST.is_denied_header(k) {
+            *v = MASK.to_string();
+        }
+    }
+}
+
+/// 将 JSON 中 deny-list 的字段打码
+pub fn mask_json(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (k, v) in map.iter_mut() {
+                if REDACT_DENY_LIST.is_denied_field(k) {
+                    *v = Value::String(MASK.to_string());
+                } else {
+                    mask_json(v);
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(mask_json),
+        _ => {}
+    }
+}
+
+/// 内容为 JSON 时将 deny-list 的字段打码, 否则保持不变
+pub fn mask_json_bytes(bytes: &mut Vec<u8>) {
+    let Ok(mut value) = serde_json::from_slice::<Value>(bytes) else {
+        return;
+    };
+    mask_json(&mut value);
+    if let Ok(masked) = serde_json::to_vec(&value) {
+        *bytes = masked;
+    }
+}
+
+/// 打印 query 参数时将 deny-list 中的值打码
+pub struct RedactedQuery<'a, V>(pub &'a HashMap<String, V>);
+
+impl<'a, V: Debug> Debug for RedactedQuery<'a, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let query: BTreeMap<&str, &dyn Debug> = self
+            .0
+            .iter()
+            .map(|(k, v)| {
+                let value: &dyn Debug = if REDACT_DENY_LIST.is_denied_param(k) { &Masked } else { v };
+                (k.as_str(), value)
+            })
+            .collect();
+        query.fmt(f)
+    }
+}
+
+/// 请求 body 为 JSON 时打码后打印, 否则只打印长度
+pub struct RedactedBody<'a>(pub &'a Option<Vec<u8>>);
+
+impl<'a> Debug for RedactedBody<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(body) = self.0 else {
+            return f.write_str("None");
+        };
+        match serde_json::from_slice::<Value>(body) {
+            Ok(mut value) => {
+                mask_json(&mut value);
+                write!(f, "{}", value)
+            }
+            Err(_) => write!(f, "<{} bytes>", body.len()),
+        }
+    }
+}
+
+/// 只打印 key, 值全部打码, 用于 secret store 的返回
+pub struct RedactedValues<'a, V>(pub &'a HashMap<String, V>);
+
+impl<'a, V> Debug for RedactedValues<'a, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let values: BTreeMap<&str, Masked> = self.0.keys().map(|k| (k.as_str(), Masked)).collect();
+        values.fmt(f)
+    }
+}
+
+/// 打印 Model 时将 `#[sensitive]` 标记的字段及 deny-list 中的字段打码
+pub struct RedactedModel<'a, T: ModelTrait>(pub &'a T);
+
+impl<'a, T: ModelTrait> Debug for RedactedModel<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ReflectRef::Struct(model) = self.0.reflect_ref() else {
+            return self.0.as_reflect().fmt(f);
+        };
+
+        let type_name = std::any::type_name::<T>().rsplit("::").next().unwrap_or_default();
+        let mut debug = f.debug_struct(type_name);
+        for i in 0..model.field_len() {
+            let (Some(name), Some(value)) = (model.name_at(i), model.field_at(i)) else {
+                continue;
+            };
+            if T::sensitive_fields().contains(&name) || REDACT_DENY_LIST.is_denied_field(name) {
+                debug.field(name, &Masked);
+            } else {
+                debug.field(name, &value);
+            }
+        }
+        debug.finish()
+    }
+}
+
+pub struct RedactedModels<'a, T: ModelTrait>(pub &'a [T]);
+
+impl<'a, T: ModelTrait> Debug for RedactedModels<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.0.iter().map(RedactedModel)).finish()
+    }
+}
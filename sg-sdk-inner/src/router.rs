@@ -0,0 +1,347 @@
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    error::Error,
+    fmt::{self, Display},
+};
+
+use http_body_util::Either;
+use hyper::{
+    header::{self, HeaderValue},
+    Method, Response, StatusCode,
+};
+use regex::Regex;
+
+use crate::{
+    body,
+    inner_biz_result::*,
+    util::{err_boxed_full, err_full, ResponseError, URI},
+    HttpResult, ROUTER,
+};
+
+pub const DEFAULT_PARAM_PATTERN: &str = "[^/]+";
+/// 自动回应的 OPTIONS 请求不属于任何 uri, metrics 中以该名称统计
+pub const OPTIONS_URI_NAME: &str = "options";
+
+/// uri! 中的 path 可以是模板, 如 `/example/{app_id:\d{19}}/env-prepare`, `{name}` 匹配单个 segment,
+/// `{name:regex}` 使用自定义的正则; 以 `^` 开头或在 `{}` 之外含有正则元字符 (`.` 除外) 的 path 仍按原始正则处理,
+/// 其中的命名分组同样作为 path 参数.
+///
+/// 匹配的优先级:
+/// 1. 模板路由优先于原始正则路由
+/// 2. 静态 segment 多的优先, 如 `/example/latest` 优先于 `/example/{app_id}`
+/// 3. 参数少的优先
+/// 4. 以上相同时按注册顺序
+#[derive(Debug, Clone)]
+pub struct Route {
+    uri: URI,
+    regex: Regex,
+    param_names: Vec<String>,
+    static_segments: usize,
+    legacy: bool,
+    /// 去掉参数名后的正则, 用于检查重复的路由
+    shape: String,
+    order: usize,
+}
+
+impl Route {
+    pub fn new(uri: URI, order: usize) -> HttpResult<Self> {
+        let path = uri.path();
+        let legacy = is_legacy_path(path);
+        let pattern = if legacy { path.to_string() } else { compile_template(path, true)? };
+        let shape = if legacy { path.to_string() } else { compile_template(path, false)? };
+        let regex = Regex::new(&pattern)?;
+        let param_names = regex.capture_names().flatten().map(|name| name.to_string()).collect();
+        let static_segments = if legacy {
+            0
+        } else {
+            path.split("/").filter(|segment| !segment.is_empty() && !segment.contains("{")).count()
+        };
+
+        Ok(Route {
+            uri,
+            regex,
+            param_names,
+            static_segments,
+            legacy,
+            shape,
+            order,
+        })
+    }
+
+    pub fn uri(&self) -> &URI {
+        &self.uri
+    }
+
+    pub fn param_names(&self) -> &[String] {
+        &self.param_names
+    }
+
+    /// 原始正则路由
+    pub fn is_legacy(&self) -> bool {
+        self.legacy
+    }
+
+    pub fn is_match(&self, path: &str) -> bool {
+        self.regex.is_match(path)
+    }
+
+    /// 匹配成功时返回命名的 path 参数
+    pub fn captures(&self, path: &str) -> Option<HashMap<String, String>> {
+        let captures = self.regex.captures(path)?;
+        Some(
+            self.param_names
+                .iter()
+                .filter_map(|name| captures.name(name).map(|value| (name.to_string(), value.as_str().to_string())))
+                .collect(),
+        )
+    }
+
+    fn precedence(&self, other: &Route) -> Ordering {
+        self.legacy
+            .cmp(&other.legacy)
+            .then(other.static_segments.cmp(&self.static_segments))
+            .then(self.param_names.len().cmp(&other.param_names.len()))
+            .then(self.order.cmp(&other.order))
+    }
+}
+
+/// 模板的静态部分只会是路径字符, 出现正则元字符说明是原始正则, 如 `/example/\d+$`;
+/// `.` 在路径中很常见, 模板中按字面量处理
+pub fn is_legacy_path(path: &str) -> bool {
+    let mut depth = 0;
+    for c in path.chars() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            '\\' | '^' | '$' | '*' | '+' | '?' | '(' | ')' | '[' | ']' | '|' if depth == 0 => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+enum TemplatePart {
+    Static(char),
+    /// 参数名及其正则
+    Param(String, String),
+}
+
+fn parse_template(template: &str) -> HttpResult<Vec<TemplatePart>> {
+    let mut parts = Vec::<TemplatePart>::new();
+    let mut chars = template.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '{' {
+            parts.push(TemplatePart::Static(c));
+            continue;
+        }
+
+        // 自定义正则中可能包含 {n}, 按层级找到匹配的 }
+        let mut depth = 1;
+        let mut param = String::new();
+        for c in chars.by_ref() {
+            match c {
+                '{' => depth += 1,
+                '}' => depth -= 1,
+                _ => {}
+            }
+            if depth == 0 {
+                break;
+            }
+            param.push(c);
+        }
+        if depth != 0 {
+            return Err(format!("route template '{}' has unclosed '{{'", template).into());
+        }
+
+        let (name, param_pattern) = param.split_once(":").unwrap_or((param.as_str(), DEFAULT_PARAM_PATTERN));
+        let name = name.trim();
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(format!("route template '{}' has invalid parameter name '{}'", template, name).into());
+        }
+        parts.push(TemplatePart::Param(name.to_string(), param_pattern.to_string()));
+    }
+
+    Ok(parts)
+}
+
+/// 将路由模板转为正则, named 为 true 时参数转为命名分组
+fn compile_template(template: &str, named: bool) -> HttpResult<String> {
+    let mut pattern = String::from("^");
+    for part in parse_template(template)? {
+        match part {
+            TemplatePart::Static(c) => pattern.push_str(&regex::escape(&c.to_string())),
+            TemplatePart::Param(name, param_pattern) if named => pattern.push_str(&format!("(?P<{}>{})", name, param_pattern)),
+            TemplatePart::Param(_, param_pattern) => pattern.push_str(&format!("({})", param_pattern)),
+        }
+    }
+    pattern.push('$');
+    Ok(pattern)
+}
+
+/// 去掉参数中的正则, 如 `/example/{app_id:\d{19}}` 转为 `/example/{app_id}`, 同时返回各参数的正则
+pub fn template_path(template: &str) -> HttpResult<(String, Vec<(String, String)>)> {
+    let mut path = String::new();
+    let mut params = Vec::<(String, String)>::new();
+    for part in parse_template(template)? {
+        match part {
+            TemplatePart::Static(c) => path.push(c),
+            TemplatePart::Param(name, param_pattern) => {
+                path.push_str(&format!("{{{}}}", name));
+                params.push((name, param_pattern));
+            }
+        }
+    }
+    Ok((path, params))
+}
+
+/// 启动时由 insert_uri 构建, 按优先级排好序
+#[derive(Debug, Default)]
+pub struct Router {
+    routes: Vec<Route>,
+}
+
+impl Router {
+    /// 模板错误或与已有路由重复时返回错误, 错误信息带上 uri 的 name
+    pub fn insert(&mut self, uri: URI) -> HttpResult<()> {
+        let name = uri.name().to_string();
+        let route = Route::new(uri, self.routes.len()).map_err(|err| format!("uri! {}: invalid path: {}", name, err))?;
+
+        // 参数名不同但正则相同的模板视为重复, 如 /a/{id} 与 /a/{app_id}
+        if let Some(exist) = self
+            .routes
+            .iter()
+            .find(|exist| exist.uri.method() == route.uri.method() && exist.shape == route.shape)
+        {
+            return Err(format!(
+                "uri! {}: route {} {} duplicates uri {}",
+                name,
+                route.uri.method(),
+                route.uri.path(),
+                exist.uri.name()
+            )
+            .into());
+        }
+
+        let pos = self.routes.partition_point(|r| r.precedence(&route) == Ordering::Less);
+        self.routes.insert(pos, route);
+        Ok(())
+    }
+
+    pub fn routes(&self) -> &[Route] {
+        &self.routes
+    }
+
+    /// 没有注册 HEAD 的路由时, HEAD 请求使用 GET 的 handler, body 由 hyper 在发送时丢弃
+    pub fn find(&self, path: &str, method: &Method) -> Option<(URI, HashMap<String, String>)> {
+        let find = |method: &Method| {
+            self.routes
+                .iter()
+                .filter(|route| route.uri.method() == method)
+                .find_map(|route| route.captures(path).map(|captures| (route.uri.clone(), captures)))
+        };
+
+        match find(method) {
+            None if method == Method::HEAD => find(&Method::GET),
+            found => found,
+        }
+    }
+
+    /// path 上注册的方法, 不含隐含的 HEAD/OPTIONS
+    pub fn registered_methods(&self, path: &str) -> Vec<Method> {
+        let mut methods = Vec::<Method>::new();
+        for route in self.routes.iter().filter(|route| route.is_match(path)) {
+            if !methods.contains(route.uri.method()) {
+                methods.push(route.uri.method().clone());
+            }
+        }
+        methods
+    }
+
+    /// path 可用的方法, GET 隐含 HEAD, 有匹配的路由时总是包含 OPTIONS
+    pub fn allowed_methods(&self, path: &str) -> Vec<Method> {
+        let mut methods = self.registered_methods(path);
+        if methods.is_empty() {
+            return methods;
+        }
+        if methods.contains(&Method::GET) && !methods.contains(&Method::HEAD) {
+            methods.push(Method::HEAD);
+        }
+        if !methods.contains(&Method::OPTIONS) {
+            methods.push(Method::OPTIONS);
+        }
+        methods
+    }
+}
+
+pub fn allow_value(methods: &[Method]) -> String {
+    methods.iter().map(|method| method.as_str()).collect::<Vec<&str>>().join(", ")
+}
+
+/// path 有匹配的路由但方法不符, 携带可用的方法用于 Allow header
+#[derive(Debug, Clone)]
+pub struct MethodNotAllowed {
+    pub allowed: Vec<Method>,
+    error: ResponseError,
+}
+
+impl MethodNotAllowed {
+    pub fn response_error(&self) -> &ResponseError {
+        &self.error
+    }
+}
+
+impl Display for MethodNotAllowed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.error, f)
+    }
+}
+
+impl Error for MethodNotAllowed {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// 路由匹配失败时区分 404 与 405
+pub async fn not_matched(path: &str, method: &Method) -> Box<dyn Error + Send + Sync> {
+    let allowed = ROUTER.read().await.allowed_methods(path);
+    if allowed.is_empty() {
+        return err_boxed_full(URI_NOT_MATCH, &format!("uri: {}, method: {}.", path, method.as_str()));
+    }
+    let error = err_full(METHOD_NOT_ALLOWED, &format!("method: {}, allow: {}", method.as_str(), allow_value(&allowed)));
+    Box::new(MethodNotAllowed { allowed, error })
+}
+
+/// MethodNotAllowed 拆分为 ResponseError 与对应的 Allow header, 其余错误原样返回
+pub fn allow_header(err: Box<dyn Error + Send + Sync>) -> (Box<dyn Error + Send + Sync>, Option<HeaderValue>) {
+    match err.downcast::<MethodNotAllowed>() {
+        Ok(not_allowed) => {
+            let allow = HeaderValue::from_str(&allow_value(&not_allowed.allowed)).ok();
+            (Box::new(not_allowed.error), allow)
+        }
+        Err(err) => (err, None),
+    }
+}
+
+/// 未注册 OPTIONS 路由时, 以路由表中的方法回应 OPTIONS 请求
+pub async fn options_http(path: &str) -> Option<Response<Either<body::Body, body::BodySt>>> {
+    let router = ROUTER.read().await;
+    if router.registered_methods(path).contains(&Method::OPTIONS) {
+        return None;
+    }
+
+    let allowed = router.allowed_methods(path);
+    if allowed.is_empty() {
+        return None;
+    }
+
+    Some(
+        Response::builder()
+            .status(StatusCode::NO_CONTENT)
+            .header(header::ALLOW, allow_value(&allowed))
+            .body(Either::Left(body::empty()))
+            .unwrap(),
+    )
+}
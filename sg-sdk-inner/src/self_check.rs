@@ -0,0 +1,162 @@
+use regex::Regex;
+use tracing::{error, info, warn};
+
+use crate::{model::ParamFrom, router, HttpResult, API_MODELS, INCOME_PARAM_MAP, URIS};
+
+/// 启动时检查 uri!, income_param!, #[uri_handler] 与 api_model! 之间的一致性, 有错误时拒绝启动;
+/// handled_uris 为空时不检查 handler, require_api_models 为 false 时缺少 api_model! 只记录警告
+pub async fn check_declarations(handled_uris: &[&str], require_api_models: bool) -> HttpResult<()> {
+    let uris = URIS.read().await.clone();
+    let income_params = INCOME_PARAM_MAP.read().await.clone();
+    let api_models = API_MODELS.read().await.clone();
+
+    let mut errors = Vec::<String>::new();
+
+    if !handled_uris.is_empty() {
+        let mut uri_names = uris.keys().collect::<Vec<&String>>();
+        uri_names.sort();
+        for uri_name in uri_names {
+            if !handled_uris.contains(&uri_name.as_str()) {
+                errors.push(format!("uri! {}: no handler in #[uri_handler]", uri_name));
+            }
+        }
+        for uri_name in handled_uris {
+            if !uris.contains_key(*uri_name) {
+                errors.push(format!("#[uri_handler] {}: uri not declared in uri! or insert_uri not called", uri_name));
+            }
+        }
+    }
+
+    // api_model! 是手写的列表, 漏写的 uri 没有 schema, 也无法判断是否为二进制输出;
+    // 只有 api 文档与原生 gRPC 依赖它, 未开启时不拒绝启动, 以免升级后原有的函数无法启动
+    let mut uri_names = uris.keys().collect::<Vec<&String>>();
+    uri_names.sort();
+    for uri_name in uri_names {
+        if api_models.contains_key(uri_name) {
+            continue;
+        }
+        match require_api_models {
+            true => errors.push(format!("uri! {}: no api_model! entry", uri_name)),
+            false => warn!("declaration check: uri! {}: no api_model! entry", uri_name),
+        }
+    }
+
+    let mut income_uri_names = income_params.keys().collect::<Vec<&String>>();
+    income_uri_names.sort();
+    for uri_name in income_uri_names {
+        let Some(uri) = uris.get(uri_name) else {
+            errors.push(format!("income_param! {}: uri not declared in uri! or insert_uri not called", uri_name));
+            continue;
+        };
+
+        // 原始正则路由的参数为其中的命名分组, segment 数量只能以其中的 `/` 估计
+        let path_shape = match router::is_legacy_path(uri.path()) {
+            true => Regex::new(uri.path()).ok().map(|regex| {
+                (
+                    regex.capture_names().flatten().map(|name| name.to_string()).collect::<Vec<String>>(),
+                    max_segments(uri.path()),
+                )
+            }),
+            false => router::template_path(uri.path()).ok().map(|(path, path_params)| {
+                let names = path_params.into_iter().map(|(name, _)| name).collect::<Vec<String>>();
+                (names, Some(path.split("/").count()))
+            }),
+        };
+        let input = api_models.get(uri_name).filter(|api_model| !api_model.input_fields.is_empty());
+
+        let mut targets = income_params[uri_name].params.iter().collect::<Vec<_>>();
+        targets.sort_by(|a, b| a.0.cmp(b.0));
+        for (target, param_def) in targets {
+            if let Some(input) = input {
+                if !input.input_fields.contains(&target.as_str()) {
+                    errors.push(format!(
+                        "income_param! ({}, {}): input model {} has no field '{}'",
+                        uri_name, target, input.input_name, target
+                    ));
+                }
+            }
+
+            let (ParamFrom::Path, Some((path_params, segments))) = (&param_def.from, &path_shape) else {
+                continue;
+            };
+            match param_def.name.parse::<usize>() {
+                Ok(position) => {
+                    let Some(segments) = *segments else {
+                        continue;
+                    };
+                    if position >= segments {
+                        errors.push(format!(
+                            "income_param! ({}, {}): path index {} out of range, '{}' has {} segments",
+                            uri_name,
+                            target,
+                            position,
+                            uri.path(),
+                            segments
+                        ));
+                    }
+                }
+                Err(_) => {
+                    if !path_params.contains(&param_def.name) {
+                        errors.push(format!(
+                            "income_param! ({}, {}): path param '{}' not in '{}'",
+                            uri_name,
+                            target,
+                            param_def.name,
+                            uri.path()
+                        ));
+                    }
+                }
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        info!("declaration check passed, {} uris", uris.len());
+        return Ok(());
+    }
+
+    for err in errors.iter() {
+        error!("declaration check: {}", err);
+    }
+    Err(format!("declaration check failed:\n{}", errors.join("\n")).into())
+}
+
+/// 原始正则最多能匹配的 segment 数量, 即字面 `/` 的个数加一; 没有首尾锚定或含 `/` 的分组可以重复时无法确定, 返回 None
+fn max_segments(pattern: &str) -> Option<usize> {
+    if !pattern.starts_with("^") || !pattern.ends_with("$") {
+        return None;
+    }
+    let mut slashes = 0;
+    // 各层分组是否包含 `/`
+    let mut groups = Vec::<bool>::new();
+    let mut closed_with_slash = false;
+    let mut in_class = false;
+    let mut chars = pattern.chars();
+
+    while let Some(c) = chars.next() {
+        let repeated = matches!(c, '*' | '+' | '{');
+        if repeated && closed_with_slash && !in_class {
+            return None;
+        }
+        closed_with_slash = false;
+
+        match c {
+            '\\' => {
+                if chars.next() == Some('/') && !in_class {
+                    slashes += 1;
+                    groups.iter_mut().for_each(|group| *group = true);
+                }
+            }
+            '[' if !in_class => in_class = true,
+            ']' if in_class => in_class = false,
+            '/' if !in_class => {
+                slashes += 1;
+                groups.iter_mut().for_each(|group| *group = true);
+            }
+            '(' if !in_class => groups.push(false),
+            ')' if !in_class => closed_with_slash = groups.pop().unwrap_or_default(),
+            _ => {}
+        }
+    }
+    Some(slashes + 1)
+}
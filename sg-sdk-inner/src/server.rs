@@ -0,0 +1,462 @@
+use std::{
+    fs::{self, File},
+    future::Future,
+    io::BufReader,
+    net::SocketAddr,
+    path::PathBuf,
+    sync::Arc,
+    time::Duration,
+};
+
+use dapr::dapr::dapr::proto::runtime::v1::{app_callback_health_check_server::AppCallbackHealthCheckServer, app_callback_server::AppCallbackServer};
+use futures_util::future::join;
+use http_body_util::Limited;
+use hyper::{body::Incoming, service::service_fn, Request};
+use hyper_util::{
+    rt::{TokioExecutor, TokioIo, TokioTimer},
+    server::conn::auto,
+};
+use tokio::{net::TcpListener, sync::Semaphore};
+use tokio_rustls::{rustls::ServerConfig, TlsAcceptor};
+use tokio_util::{sync::CancellationToken, task::TaskTracker};
+use tonic::transport::{server::Router, Identity, Server, ServerTlsConfig};
+use tracing::{error, info, warn};
+
+use crate::{
+    native_grpc::NativeGrpcService,
+    plugin, proto, self_check, shutdown,
+    start::{http_service, GrpcService},
+    telemetry,
+    traits::{GrpcRequestDispatcherTrait, HttpRequestDispatcherTrait, NativeGrpcDispatcherTrait},
+    HttpResult, SHUTDOWN_TOKEN, TASK_TRACKER,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HttpProtocol {
+    Http1,
+    /// HTTP/2, 明文时为 h2c prior knowledge
+    Http2,
+    /// 依据连接的 preface 自动识别 HTTP/1.1 或 HTTP/2
+    Auto,
+}
+
+#[derive(Debug, Clone)]
+pub struct ServerBuilder {
+    http_addr: Option<SocketAddr>,
+    http_protocol: HttpProtocol,
+    grpc_addr: Option<SocketAddr>,
+    keep_alive: bool,
+    header_read_timeout: Option<Duration>,
+    max_connections: Option<usize>,
+    max_body_size: usize,
+    tls: Option<(PathBuf, PathBuf)>,
+    /// 开启原生 gRPC 服务时, 将其加入 grpc server 的函数
+    native_grpc: Option<fn(Router, usize) -> Router>,
+    api_docs: bool,
+    /// 默认为全局的 SHUTDOWN_TOKEN 与 TASK_TRACKER
+    shutdown_token: CancellationToken,
+    task_tracker: TaskTracker,
+}
+
+impl Default for ServerBuilder {
+    fn default() -> Self {
+        ServerBuilder {
+            http_addr: None,
+            http_protocol: HttpProtocol::Http1,
+            grpc_addr: None,
+            keep_alive: true,
+            header_read_timeout: None,
+            max_connections: None,
+            max_body_size: usize::MAX,
+            tls: None,
+            native_grpc: None,
+            api_docs: false,
+            shutdown_token: SHUTDOWN_TOKEN.clone(),
+            task_tracker: TASK_TRACKER.clone(),
+        }
+    }
+}
+
+impl ServerBuilder {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn http_addr(mut self, addr: SocketAddr) -> Self {
+        self.http_addr = Some(addr);
+        self
+    }
+
+    pub fn http_port(self, port: u16) -> Self {
+        self.http_addr(SocketAddr::from(([0, 0, 0, 0], port)))
+    }
+
+    pub fn http_protocol(mut self, http_protocol: HttpProtocol) -> Self {
+        self.http_protocol = http_protocol;
+        self
+    }
+
+    pub fn grpc_addr(mut self, addr: SocketAddr) -> Self {
+        self.grpc_addr = Some(addr);
+        self
+    }
+
+    pub fn grpc_port(self, port: u16) -> Self {
+        self.grpc_addr(SocketAddr::from(([0, 0, 0, 0], port)))
+    }
+
+    pub fn keep_alive(mut self, keep_alive: bool) -> Self {
+        self.keep_alive = keep_alive;
+        self
+    }
+
+    pub fn header_read_timeout(mut self, timeout: Duration) -> Self {
+        self.header_read_timeout = Some(timeout);
+        self
+    }
+
+    pub fn max_connections(mut self, max_connections: usize) -> Self {
+        self.max_connections = Some(max_connections);
+        self
+    }
+
+    pub fn max_body_size(mut self, max_body_size: usize) -> Self {
+        self.max_body_size = max_body_size;
+        self
+    }
+
+    pub fn tls<C: Into<PathBuf>, K: Into<PathBuf>>(mut self, cert_path: C, key_path: K) -> Self {
+        self.tls = Some((cert_path.into(), key_path.into()));
+        self
+    }
+
+    /// 在 grpc 端口上同时提供原生 gRPC 服务, 每个 URI 一个 RPC, 默认关闭
+    pub fn native_grpc<NativeDispatcher: NativeGrpcDispatcherTrait + Send + Copy + Sync + 'static>(mut self) -> Self {
+        self.native_grpc = Some(add_native_grpc_service::<NativeDispatcher>);
+        self
+    }
+
+    /// 在 http 端口上提供 OPENAPI_PATH 与 PROTO_PATH, 这两个路径不经鉴权, 默认关闭
+    pub fn api_docs(mut self) -> Self {
+        self.api_docs = true;
+        self
+    }
+
+    pub async fn serve<OneDispatcher: HttpRequestDispatcherTrait + GrpcRequestDispatcherTrait + Send + Copy + Sync + 'static>(self) -> HttpResult<()> {
+        let uri_names = <OneDispatcher as HttpRequestDispatcherTrait>::uri_names();
+        self.check_startup(uri_names, self.api_docs || self.native_grpc.is_some()).await?;
+
+        let res = match (self.http_addr, self.grpc_addr) {
+            (Some(http_addr), Some(grpc_addr)) => {
+                let (http_res, grpc_res) = join(
+                    self.shutdown_on_err("http", self.run_http::<OneDispatcher>(http_addr)),
+                    self.shutdown_on_err("grpc", self.run_grpc::<OneDispatcher>(grpc_addr)),
+                )
+                .await;
+                http_res.and(grpc_res)
+            }
+            (Some(http_addr), None) => self.run_http::<OneDispatcher>(http_addr).await,
+            (None, Some(grpc_addr)) => self.run_grpc::<OneDispatcher>(grpc_addr).await,
+            (None, None) => return Err("neither http nor grpc address is set".into()),
+        };
+
+        self.finish_shutdown().await;
+        res
+    }
+
+    pub async fn serve_http<HttpDispatcher: HttpRequestDispatcherTrait + Send + Copy + 'static>(&self) -> HttpResult<()> {
+        let http_addr = self.http_addr.ok_or("http address not set")?;
+        let uri_names = <HttpDispatcher as HttpRequestDispatcherTrait>::uri_names();
+        self.check_startup(uri_names, self.api_docs).await?;
+
+        let res = self.run_http::<HttpDispatcher>(http_addr).await;
+        self.finish_shutdown().await;
+        res
+    }
+
+    pub async fn serve_grpc<GrpcDispatcher: GrpcRequestDispatcherTrait + Send + Copy + Sync + 'static>(&self) -> HttpResult<()> {
+        let grpc_addr = self.grpc_addr.ok_or("grpc address not set")?;
+        let uri_names = <GrpcDispatcher as GrpcRequestDispatcherTrait>::uri_names();
+        self.check_startup(uri_names, self.native_grpc.is_some()).await?;
+
+        let res = self.run_grpc::<GrpcDispatcher>(grpc_addr).await;
+        self.finish_shutdown().await;
+        res
+    }
+
+    /// 启动前的检查, serve/serve_http/serve_grpc 各只执行一次; require_api_models 时 api_model! 与 proto 类型必须完整
+    async fn check_startup(&self, uri_names: &[&str], require_api_models: bool) -> HttpResult<()> {
+        plugin::check_plugins().await?;
+        self_check::check_declarations(uri_names, require_api_models).await?;
+        if require_api_models {
+            proto::check_types().await?;
+        }
+
+        info!("function started OK, now for serving...");
+        Ok(())
+    }
+
+    /// server 全部停止后执行 shutdown hook 并关闭 tracer
+    async fn finish_shutdown(&self) {
+        shutdown::run_shutdown_hooks().await;
+        telemetry::shutdown_tracer();
+        info!("function shutdown OK");
+    }
+
+    /// 任一 server 出错 (如端口绑定失败) 时立即触发 shutdown, 另一个 server 随之停止, 而不是等到收到信号
+    async fn shutdown_on_err(&self, name: &str, serve: impl Future<Output = HttpResult<()>>) -> HttpResult<()> {
+        let res = serve.await;
+        if let Err(err) = &res {
+            error!("{} server error: {:?}", name, err);
+            self.shutdown_token.cancel();
+        }
+        res
+    }
+
+    async fn run_http<HttpDispatcher: HttpRequestDispatcherTrait + Send + Copy + 'static>(&self, http_addr: SocketAddr) -> HttpResult<()> {
+        let tls_acceptor = match &self.tls {
+            None => None,
+            Some((cert_path, key_path)) => Some(tls_acceptor(cert_path, key_path, self.http_protocol)?),
+        };
+        let connection_limit = self.max_connections.map(|n| Arc::new(Semaphore::new(n)));
+
+        let listener = TcpListener::bind(http_addr).await?;
+        info!(addr = %http_addr, protocol = ?self.http_protocol, tls = tls_acceptor.is_some(), "listening on http");
+
+        shutdown::listen_signal();
+
+        loop {
+            let permit = match &connection_limit {
+                None => None,
+                Some(limit) => tokio::select! {
+                    permit = limit.clone().acquire_owned() => Some(permit?),
+                    _ = self.shutdown_token.cancelled() => break,
+                },
+            };
+
+            let (stream, remote_addr) = tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok(accepted) => accepted,
+                    Err(err) => {
+                        error!("Error accepting connection: {:?}", err);
+                        continue;
+                    }
+                },
+                _ = self.shutdown_token.cancelled() => break,
+            };
+
+            let http_builder = self.http_builder();
+            let tls_acceptor = tls_acceptor.clone();
+            let max_body_size = self.max_body_size;
+            let api_docs = self.api_docs;
+            let shutdown_token = self.shutdown_token.clone();
+
+            self.task_tracker.spawn(async move {
+                let _permit = permit;
+
+                match tls_acceptor {
+                    None => {
+                        serve_connection::<HttpDispatcher, _>(TokioIo::new(stream), remote_addr, http_builder, max_body_size, api_docs, shutdown_token).await
+                    }
+                    Some(acceptor) => match acceptor.accept(stream).await {
+                        Ok(tls_stream) => {
+                            serve_connection::<HttpDispatcher, _>(TokioIo::new(tls_stream), remote_addr, http_builder, max_body_size, api_docs, shutdown_token)
+                                .await
+                        }
+                        Err(err) => error!("Error tls handshake: {:?}", err),
+                    },
+                }
+            });
+        }
+
+        info!("http server stop accepting connections");
+        shutdown::drain_connections(&self.task_tracker).await;
+        Ok(())
+    }
+
+    async fn run_grpc<GrpcDispatcher: GrpcRequestDispatcherTrait + Send + Copy + Sync + 'static>(&self, grpc_addr: SocketAddr) -> HttpResult<()> {
+        let mut server = Server::builder();
+        if let Some((cert_path, key_path)) = &self.tls {
+            let identity = Identity::from_pem(fs::read(cert_path)?, fs::read(key_path)?);
+            server = server.tls_config(ServerTlsConfig::new().identity(identity))?;
+        }
+
+        let mut callback_service = AppCallbackServer::new(GrpcService::<GrpcDispatcher>::new());
+        if self.max_body_size < usize::MAX {
+            callback_service = callback_service.max_decoding_message_size(self.max_body_size);
+        }
+
+        let mut router = server
+            .add_service(callback_service)
+            .add_service(AppCallbackHealthCheckServer::new(GrpcService::<GrpcDispatcher>::new()));
+        if let Some(add_native_grpc) = self.native_grpc {
+            router = add_native_grpc(router, self.max_body_size);
+        }
+
+        info!(addr = %grpc_addr, tls = self.tls.is_some(), native = self.native_grpc.is_some(), "listening on grpc");
+
+        shutdown::listen_signal();
+
+        let serve = router.serve_with_shutdown(grpc_addr, self.shutdown_token.cancelled());
+
+        let drain_deadline = async {
+            self.shutdown_token.cancelled().await;
+            tokio::time::sleep(shutdown::drain_timeout().await).await;
+        };
+
+        tokio::select! {
+            res = serve => res?,
+            _ = drain_deadline => warn!("grpc drain timeout, {} requests still in flight", shutdown::in_flight()),
+        }
+
+        info!("grpc server stopped");
+        Ok(())
+    }
+
+    fn http_builder(&self) -> auto::Builder<TokioExecutor> {
+        let mut http_builder = auto::Builder::new(TokioExecutor::new());
+        http_builder.http1().keep_alive(self.keep_alive);
+        http_builder.http2().timer(TokioTimer::new());
+        if let Some(timeout) = self.header_read_timeout {
+            http_builder.http1().timer(TokioTimer::new()).header_read_timeout(timeout);
+        }
+
+        match self.http_protocol {
+            HttpProtocol::Http1 => http_builder.http1_only(),
+            HttpProtocol::Http2 => http_builder.http2_only(),
+            HttpProtocol::Auto => http_builder,
+        }
+    }
+}
+
+fn add_native_grpc_service<NativeDispatcher: NativeGrpcDispatcherTrait + Send + Copy + Sync + 'static>(router: Router, max_body_size: usize) -> Router {
+    let mut native_service = NativeGrpcService::<NativeDispatcher>::new();
+    if max_body_size < usize::MAX {
+        native_service = native_service.max_decoding_message_size(max_body_size);
+    }
+    router.add_service(native_service)
+}
+
+async fn serve_connection<HttpDispatcher, IO>(
+    io: IO,
+    remote_addr: SocketAddr,
+    http_builder: auto::Builder<TokioExecutor>,
+    max_body_size: usize,
+    api_docs: bool,
+    shutdown_token: CancellationToken,
+) where
+    HttpDispatcher: HttpRequestDispatcherTrait + Send + Copy + 'static,
+    IO: hyper::rt::Read + hyper::rt::Write + Unpin + Send + 'static,
+{
+    let conn = http_builder.serve_connection(
+        io,
+        service_fn(move |mut req: Request<Incoming>| {
+            // 对端地址随请求传递, 供按地址限流使用
+            req.extensions_mut().insert(remote_addr);
+            http_service::<HttpDispatcher>(req.map(|body| Limited::new(body, max_body_size)), api_docs)
+        }),
+    );
+    tokio::pin!(conn);
+
+    let res = tokio::select! {
+        res = conn.as_mut() => res,
+        _ = shutdown_token.cancelled() => {
+            conn.as_mut().graceful_shutdown();
+            conn.as_mut().await
+        }
+    };
+
+    if let Err(err) = res {
+        error!("Error serving connection: {:?}", err);
+    }
+}
+
+fn tls_acceptor(cert_path: &PathBuf, key_path: &PathBuf, http_protocol: HttpProtocol) -> HttpResult<TlsAcceptor> {
+    let certs = rustls_pemfile::certs(&mut BufReader::new(File::open(cert_path)?)).collect::<Result<Vec<_>, _>>()?;
+    let key = rustls_pemfile::private_key(&mut BufReader::new(File::open(key_path)?))?.ok_or("tls private key not found")?;
+
+    let mut config = ServerConfig::builder().with_no_client_auth().with_single_cert(certs, key)?;
+    config.alpn_protocols = match http_protocol {
+        HttpProtocol::Http1 => vec![b"http/1.1".to_vec()],
+        HttpProtocol::Http2 => vec![b"h2".to_vec()],
+        HttpProtocol::Auto => vec![b"h2".to_vec(), b"http/1.1".to_vec()],
+    };
+
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use http_body_util::{Either, Full};
+    use hyper::{Method, Response};
+    use lazy_static::lazy_static;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpStream,
+        sync::Notify,
+    };
+
+    use super::*;
+    use crate::{
+        body,
+        model::{Action, Params},
+        util::{self, URI},
+    };
+
+    const SLOW_URI: URI = URI(Method::GET, "/slow", "SLOW", Action::Query, false, false);
+
+    lazy_static! {
+        static ref HANDLER_STARTED: Notify = Notify::new();
+    }
+
+    #[derive(Clone, Copy)]
+    struct SlowDispatcher;
+
+    impl HttpRequestDispatcherTrait for SlowDispatcher {
+        async fn do_http_dispatch(_params: Params) -> HttpResult<Response<Either<body::Body, body::BodySt>>> {
+            HANDLER_STARTED.notify_one();
+            tokio::time::sleep(Duration::from_millis(300)).await;
+            Ok(Response::new(Either::Left(Either::Right(Full::new(Bytes::from("slow done"))))))
+        }
+    }
+
+    #[tokio::test]
+    async fn slow_request_completes_during_shutdown() {
+        util::insert_uri(SLOW_URI).await.unwrap();
+        util::set_skip_auth_uri(SLOW_URI).await.unwrap();
+
+        let addr = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        // 独立的 token 与 tracker, 不影响全局的 shutdown 状态
+        let shutdown_token = CancellationToken::new();
+        let server = ServerBuilder {
+            shutdown_token: shutdown_token.clone(),
+            task_tracker: TaskTracker::new(),
+            ..ServerBuilder::new().http_addr(addr)
+        };
+
+        let client = async {
+            let mut stream = loop {
+                match TcpStream::connect(addr).await {
+                    Ok(stream) => break stream,
+                    Err(_) => tokio::time::sleep(Duration::from_millis(10)).await,
+                }
+            };
+            stream.write_all(b"GET /slow HTTP/1.1\r\nhost: localhost\r\n\r\n").await.unwrap();
+
+            // 与收到 SIGTERM 时相同, 请求处理中取消 token
+            HANDLER_STARTED.notified().await;
+            shutdown_token.cancel();
+
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            response
+        };
+
+        let (serve_res, response) = tokio::join!(server.serve_http::<SlowDispatcher>(), client);
+
+        serve_res.unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+        assert!(response.ends_with("slow done"), "{}", response);
+    }
+}
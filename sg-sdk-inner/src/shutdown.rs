@@ -0,0 +1,125 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    time::Duration,
+};
+
+use tokio::time::timeout;
+use tokio_util::task::TaskTracker;
+use tracing::{error, info, warn};
+
+use crate::{HttpResult, DRAIN_TIMEOUT, SHUTDOWN_HOOKS, SHUTDOWN_TOKEN};
+
+static IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+static SIGNAL_LISTENING: AtomicBool = AtomicBool::new(false);
+
+pub type ShutdownHook = fn() -> Pin<Box<dyn Future<Output = HttpResult<()>> + Send>>;
+
+pub struct InFlightGuard;
+
+impl InFlightGuard {
+    pub fn new() -> Self {
+        IN_FLIGHT.fetch_add(1, Ordering::SeqCst);
+        InFlightGuard
+    }
+}
+
+impl Default for InFlightGuard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        IN_FLIGHT.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+pub fn in_flight() -> usize {
+    IN_FLIGHT.load(Ordering::SeqCst)
+}
+
+pub fn is_shutting_down() -> bool {
+    SHUTDOWN_TOKEN.is_cancelled()
+}
+
+pub fn shutdown() {
+    SHUTDOWN_TOKEN.cancel();
+}
+
+pub async fn set_drain_timeout(drain_timeout: Duration) -> HttpResult<()> {
+    info!("set shutdown drain timeout: {:?}", drain_timeout);
+    let mut t = DRAIN_TIMEOUT.write().await;
+    *t = drain_timeout;
+    Ok(())
+}
+
+pub async fn drain_timeout() -> Duration {
+    *DRAIN_TIMEOUT.read().await
+}
+
+pub async fn insert_shutdown_hook(name: &str, hook: ShutdownHook) -> HttpResult<()> {
+    info!("set shutdown hook: {}", name);
+    let mut hooks = SHUTDOWN_HOOKS.write().await;
+    hooks.push((name.to_string(), hook));
+    Ok(())
+}
+
+/// 监听 SIGTERM/SIGINT, 收到后取消 SHUTDOWN_TOKEN, 多次调用只会监听一次
+pub fn listen_signal() {
+    if SIGNAL_LISTENING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    tokio::spawn(async move {
+        #[cfg(unix)]
+        let terminate = async {
+            match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+                Ok(mut sig) => {
+                    sig.recv().await;
+                }
+                Err(err) => {
+                    error!("listen SIGTERM error: {}", err);
+                    std::future::pending::<()>().await;
+                }
+            }
+        };
+
+        #[cfg(not(unix))]
+        let terminate = std::future::pending::<()>();
+
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => info!("SIGINT received"),
+            _ = terminate => info!("SIGTERM received"),
+            _ = SHUTDOWN_TOKEN.cancelled() => return,
+        }
+
+        shutdown();
+    });
+}
+
+/// 等待已接收的连接处理完毕, 超过 drain timeout 后放弃
+pub async fn drain_connections(tracker: &TaskTracker) {
+    tracker.close();
+
+    let drain_timeout = drain_timeout().await;
+    info!("draining connections, {} requests in flight, timeout {:?}", in_flight(), drain_timeout);
+
+    match timeout(drain_timeout, tracker.wait()).await {
+        Ok(_) => info!("all connections drained"),
+        Err(_) => warn!("drain timeout, {} requests still in flight", in_flight()),
+    }
+}
+
+pub async fn run_shutdown_hooks() {
+    let hooks = SHUTDOWN_HOOKS.read().await;
+
+    for (name, hook) in hooks.iter() {
+        info!("run shutdown hook: {}", name);
+        if let Err(err) = hook().await {
+            error!("shutdown hook '{}' error: {:?}", name, err);
+        }
+    }
+}
@@ -1,30 +1,43 @@
-use std::{collections::HashMap, net::SocketAddr, str::FromStr};
+use std::{collections::HashMap, str::FromStr, time::Instant};
 
+use bevy_reflect::Reflect;
 use dapr::{
     appcallback::{
         BindingEventRequest, BindingEventResponse, InvokeRequest, InvokeResponse, ListInputBindingsResponse, ListTopicSubscriptionsResponse, TopicEventRequest,
         TopicEventResponse,
     },
-    dapr::dapr::proto::runtime::v1::app_callback_server::{AppCallback, AppCallbackServer},
+    dapr::dapr::proto::runtime::v1::{app_callback_health_check_server::AppCallbackHealthCheck, app_callback_server::AppCallback, HealthCheckResponse},
 };
-use futures_util::future::join;
 use http_body_util::Either;
-use hyper::{body::Incoming, server::conn::http1, service::service_fn, Request, Response};
-use hyper_util::rt::TokioIo;
+use hyper::{
+    header::{self, HeaderValue},
+    Method, Request, Response,
+};
 use prost::Message;
 use serde::Serialize;
-use tokio::net::TcpListener;
 use tonic::{
-    metadata::{MetadataKey, MetadataValue},
-    transport::Server,
+    metadata::{MetadataKey, MetadataMap, MetadataValue},
     Status,
 };
-use tracing::{debug, error, info};
+use tracing::{debug, field, info_span, Instrument};
 
 use crate::{
-    body,
+    binding, body,
+    health::{self, HEALTHZ_PATH, READYZ_PATH},
+    idempotency::{self, Idempotent, Protocol},
     inner_biz_result::*,
+    log,
+    metrics::{self, BizCode, METRICS_PATH},
+    middleware,
     model::{IfRes, Params},
+    openapi::{self, OPENAPI_PATH},
+    plugin,
+    proto::{self, PROTO_PATH},
+    pubsub::{self, DAPR_SUBSCRIBE_PATH},
+    rate_limit, router,
+    server::ServerBuilder,
+    shutdown, status,
+    telemetry::{self, HeaderExtractor, MetadataExtractor},
     util::{self, auth_ict, find_response_auth_header, parse_params_grpc},
     GrpcResult, HttpResult, *,
 };
@@ -32,69 +45,124 @@ use crate::{
 use self::traits::{DaprBody, GrpcRequestDispatcherTrait, HttpRequestDispatcherTrait, ModelTrait};
 
 pub async fn start_http<HttpDispatcher: HttpRequestDispatcherTrait + Send + Copy + 'static>(port: u16) -> HttpResult<()> {
-    let http_addr = SocketAddr::from(([0, 0, 0, 0], port));
-
-    info!("Listening on http port {}", http_addr);
-
-    let http_serve = async move {
-        let listener = TcpListener::bind(http_addr).await.unwrap();
-        loop {
-            let (stream, _) = listener.accept().await.unwrap();
-
-            tokio::task::spawn(async move {
-                if let Err(err) = http1::Builder::new()
-                    .serve_connection(TokioIo::new(stream), service_fn(move |req| http_service::<HttpDispatcher>(req)))
-                    .await
-                {
-                    error!("Error serving connection: {:?}", err);
-                }
-            });
-        }
-    };
-
-    http_serve.await
+    ServerBuilder::new().http_port(port).serve_http::<HttpDispatcher>().await
 }
 
 pub async fn start_grpc<GrpcDispatcher: GrpcRequestDispatcherTrait + Send + Copy + Sync + 'static>(port: u16) -> HttpResult<()> {
-    let grpc_addr = SocketAddr::from(([0, 0, 0, 0], port));
-
-    info!("Listening on grpc port {}", grpc_addr);
-
-    let grpc_serve = async move {
-        let callback_service = GrpcService::<GrpcDispatcher> { _placeholder: None };
-
-        Server::builder()
-            .add_service(AppCallbackServer::new(callback_service))
-            .serve(grpc_addr)
-            .await
-            .unwrap();
-        Ok(())
-    };
-
-    grpc_serve.await
+    ServerBuilder::new().grpc_port(port).serve_grpc::<GrpcDispatcher>().await
 }
 
 pub async fn start_http_grpc<OneDispatcher: HttpRequestDispatcherTrait + GrpcRequestDispatcherTrait + Send + Copy + Sync + 'static>(
     http_port: u16,
     grpc_port: u16,
 ) -> HttpResult<()> {
-    info!("function started OK, now for serving...");
-    let _ = join(start_http::<OneDispatcher>(http_port), start_grpc::<OneDispatcher>(grpc_port)).await;
+    ServerBuilder::new().http_port(http_port).grpc_port(grpc_port).serve::<OneDispatcher>().await
+}
 
-    Ok(())
+pub(crate) async fn http_service<HttpDispatcher: HttpRequestDispatcherTrait + Send + Copy + 'static>(
+    req: Request<body::ReqBody>,
+    api_docs: bool,
+) -> HttpResult<Response<Either<body::Body, body::BodySt>>> {
+    let header = |key: &str| req.headers().get(key).and_then(|v| v.to_str().ok());
+    let request_id = log::request_id(header(log::REQUEST_ID_HEADER));
+    let span = info_span!(
+        "request",
+        otel.kind = "server",
+        http.method = %req.method(),
+        http.target = %req.uri().path(),
+        request_id = %request_id,
+        caller_app_id = header(log::CALLER_APP_ID_HEADER),
+        uri_name = field::Empty,
+        saga_id = field::Empty,
+    );
+    telemetry::set_parent_from(&span, &HeaderExtractor(req.headers()));
+
+    let header_value = HeaderValue::from_str(&request_id);
+    let mut res = log::with_request_id(request_id, serve_request::<HttpDispatcher>(req, api_docs))
+        .instrument(span)
+        .await;
+    if let (Ok(response), Ok(header_value)) = (&mut res, header_value) {
+        response.headers_mut().insert(log::REQUEST_ID_HEADER, header_value);
+    }
+    res
 }
 
-async fn http_service<HttpDispatcher: HttpRequestDispatcherTrait + Send + Copy + 'static>(
-    req: Request<Incoming>,
+async fn serve_request<HttpDispatcher: HttpRequestDispatcherTrait + Send + Copy + 'static>(
+    req: Request<body::ReqBody>,
+    api_docs: bool,
 ) -> HttpResult<Response<Either<body::Body, body::BodySt>>> {
+    let _in_flight = shutdown::InFlightGuard::new();
+    let started = Instant::now();
+
+    if req.method() == Method::GET {
+        match req.uri().path() {
+            HEALTHZ_PATH => return Ok(health::liveness()),
+            METRICS_PATH => return Ok(metrics::metrics_http()),
+            READYZ_PATH => return Ok(health::readiness().await),
+            DAPR_SUBSCRIBE_PATH => return Ok(pubsub::list_topic_subscriptions_http().await),
+            OPENAPI_PATH if api_docs => return Ok(openapi::openapi_http().await),
+            PROTO_PATH if api_docs => return Ok(proto::proto_http().await),
+            _ => {}
+        }
+    }
+
+    if req.method() == Method::POST {
+        if let Some((topic, handler)) = pubsub::find_topic_by_route(req.uri().path()).await {
+            return Ok(pubsub::on_topic_event_http(topic, handler, req).await);
+        }
+    }
+
+    let binding_name = req.uri().path().trim_start_matches('/');
+    if let Some((input_binding, handler)) = binding::find_input_binding(binding_name).await {
+        if req.method() == Method::OPTIONS {
+            return Ok(binding::binding_options_http());
+        }
+        if req.method() == Method::POST {
+            return Ok(binding::on_binding_event_http(input_binding, handler, req).await);
+        }
+    }
+
+    if req.method() == Method::OPTIONS {
+        if let Some(response) = router::options_http(req.uri().path()).await {
+            let res = Ok(response);
+            metrics::observe_http(router::OPTIONS_URI_NAME, None, &res, started);
+            return res;
+        }
+    }
+
     let params = util::parse_params(req).await;
-    let mut params = match params {
+    let params = match params {
         Ok(params) => params,
         Err(err) => {
-            return Ok(util::err_resolve(err).await);
+            let (err, allow) = router::allow_header(err);
+            let mut response = util::err_resolve(err).await;
+            if let Some(allow) = allow {
+                response.headers_mut().insert(header::ALLOW, allow);
+            }
+            let res = Ok(response);
+            metrics::observe_http("unknown", None, &res, started);
+            return res;
         }
     };
 
+    log::record_params(&params);
+    let (uri_name, action) = (params.uri.clone(), params.if_info.action.clone());
+    let res = match util::catch_panic(dispatch_http::<HttpDispatcher>(params)).await {
+        Ok(res) => res,
+        Err(err) => Ok(util::err_resolve(err).await),
+    };
+    metrics::observe_http(&uri_name, Some(&action), &res, started);
+    res
+}
+
+async fn dispatch_http<HttpDispatcher: HttpRequestDispatcherTrait + Send + Copy + 'static>(
+    mut params: Params,
+) -> HttpResult<Response<Either<body::Body, body::BodySt>>> {
+    let middlewares = middleware::middlewares().await;
+    if let Err(err) = middleware::before(&middlewares, &mut params).await {
+        return Ok(util::err_resolve(err).await);
+    }
+
     match auth_ict(&mut params).await {
         Ok(_) => {}
         Err(err) => {
@@ -102,59 +170,197 @@ async fn http_service<HttpDispatcher: HttpRequestDispatcherTrait + Send + Copy +
         }
     };
 
-    HttpDispatcher::do_http_dispatch(params).await
+    let _permit = match rate_limit::check(&params).await {
+        Ok(permit) => permit,
+        Err(err) => return Ok(util::err_resolve(err).await),
+    };
+
+    if let Err(err) = plugin::run_pre_plugins(&mut params).await {
+        return Ok(util::err_resolve(err).await);
+    }
+
+    if middlewares.is_empty() {
+        return dispatch_idempotent_http::<HttpDispatcher>(params).await;
+    }
+
+    let request_params = params.clone();
+    let mut response = dispatch_idempotent_http::<HttpDispatcher>(params).await?;
+    if let Err(err) = middleware::after_http(&middlewares, &request_params, &mut response).await {
+        return Ok(util::err_resolve(err).await);
+    }
+
+    Ok(response)
+}
+
+async fn dispatch_idempotent_http<HttpDispatcher: HttpRequestDispatcherTrait + Send + Copy + 'static>(
+    params: Params,
+) -> HttpResult<Response<Either<body::Body, body::BodySt>>> {
+    let guard = match idempotency::begin(&params, Protocol::Http).await {
+        Ok(Idempotent::Skip) => None,
+        Ok(Idempotent::Replay(record)) => return Ok(idempotency::replay_http(record)),
+        Ok(Idempotent::Proceed(guard)) => Some(guard),
+        Err(err) => return Ok(util::err_resolve(err).await),
+    };
+
+    idempotency::finish_http(guard, HttpDispatcher::do_http_dispatch(params).await).await
 }
 
 pub struct GrpcService<GrpcDispatcher: GrpcRequestDispatcherTrait + Send + Copy + 'static> {
     _placeholder: Option<GrpcDispatcher>,
 }
 
+impl<GrpcDispatcher: GrpcRequestDispatcherTrait + Send + Copy + 'static> GrpcService<GrpcDispatcher> {
+    pub fn new() -> Self {
+        GrpcService { _placeholder: None }
+    }
+}
+
 #[tonic::async_trait]
 impl<GrpcDispatcher: GrpcRequestDispatcherTrait + Send + Copy + Sync> AppCallback for GrpcService<GrpcDispatcher> {
     async fn on_invoke(&self, request: tonic::Request<InvokeRequest>) -> GrpcResult<tonic::Response<InvokeResponse>> {
-        debug!("grpc request: {:?}", &request);
-
-        let params = parse_params_grpc(request).await;
-        let mut params = match params {
-            Ok(params) => params,
-            Err(err) => {
-                return GrpcResult::Err(err);
-            }
-        };
-
-        match auth_ict(&mut params).await {
-            Ok(_) => {}
-            Err(err) => {
-                return GrpcResult::Err(Status::unauthenticated(err.to_string()));
-            }
-        };
+        let metadata = |key: &str| request.metadata().get(key).and_then(|v| v.to_str().ok());
+        let request_id = log::request_id(metadata(log::REQUEST_ID_HEADER));
+        let span = info_span!(
+            "request",
+            otel.kind = "server",
+            rpc.method = %request.get_ref().method,
+            request_id = %request_id,
+            caller_app_id = metadata(log::CALLER_APP_ID_HEADER),
+            uri_name = field::Empty,
+            saga_id = field::Empty,
+        );
+        telemetry::set_parent_from(&span, &MetadataExtractor(request.metadata()));
 
-        GrpcDispatcher::do_grpc_dispatch(params).await
+        let metadata_value = MetadataValue::try_from(request_id.as_str());
+        let mut res = log::with_request_id(request_id, invoke_request::<GrpcDispatcher>(request))
+            .instrument(span)
+            .await;
+        if let Ok(value) = metadata_value {
+            match &mut res {
+                Ok(response) => response.metadata_mut().insert(log::REQUEST_ID_HEADER, value),
+                Err(status) => status.metadata_mut().insert(log::REQUEST_ID_HEADER, value),
+            };
+        }
+        res
     }
 
     async fn list_topic_subscriptions(&self, _request: tonic::Request<()>) -> GrpcResult<tonic::Response<ListTopicSubscriptionsResponse>> {
-        let list_subscriptions = ListTopicSubscriptionsResponse::default();
+        let list_subscriptions = pubsub::list_topic_subscriptions().await;
         Ok(tonic::Response::new(list_subscriptions))
     }
 
-    async fn on_topic_event(&self, _request: tonic::Request<TopicEventRequest>) -> GrpcResult<tonic::Response<TopicEventResponse>> {
-        Ok(tonic::Response::new(TopicEventResponse::default()))
+    async fn on_topic_event(&self, request: tonic::Request<TopicEventRequest>) -> GrpcResult<tonic::Response<TopicEventResponse>> {
+        let _in_flight = shutdown::InFlightGuard::new();
+        let event = request.get_ref();
+        debug!("grpc topic event: {}/{}, id: {}", event.pubsub_name, event.topic, event.id);
+        Ok(tonic::Response::new(pubsub::on_topic_event_grpc(request.into_inner()).await))
     }
 
     async fn list_input_bindings(&self, _request: tonic::Request<()>) -> GrpcResult<tonic::Response<ListInputBindingsResponse>> {
-        Ok(tonic::Response::new(ListInputBindingsResponse::default()))
+        Ok(tonic::Response::new(binding::list_input_bindings().await))
     }
 
-    async fn on_binding_event(&self, _request: tonic::Request<BindingEventRequest>) -> GrpcResult<tonic::Response<BindingEventResponse>> {
-        Ok(tonic::Response::new(BindingEventResponse::default()))
+    async fn on_binding_event(&self, request: tonic::Request<BindingEventRequest>) -> GrpcResult<tonic::Response<BindingEventResponse>> {
+        let _in_flight = shutdown::InFlightGuard::new();
+        debug!("grpc binding event: {}, {} bytes", request.get_ref().name, request.get_ref().data.len());
+        Ok(tonic::Response::new(binding::on_binding_event_grpc(request.into_inner()).await?))
+    }
+}
+
+async fn invoke_request<GrpcDispatcher: GrpcRequestDispatcherTrait + Send + Copy + Sync>(
+    request: tonic::Request<InvokeRequest>,
+) -> GrpcResult<tonic::Response<InvokeResponse>> {
+    let _in_flight = shutdown::InFlightGuard::new();
+    let started = Instant::now();
+    // metadata 中带有 x-sg-auth-* 等凭证, 只打印方法
+    debug!("grpc request: {}, content type: {}", request.get_ref().method, request.get_ref().content_type);
+
+    let params = parse_params_grpc(request).await;
+    let params = match params {
+        Ok(params) => params,
+        Err(err) => {
+            let res = GrpcResult::Err(err);
+            metrics::observe_grpc("unknown", None, &res, started);
+            return res;
+        }
+    };
+
+    log::record_params(&params);
+    let (uri_name, action) = (params.uri.clone(), params.if_info.action.clone());
+    let res = match util::catch_panic(dispatch_grpc::<GrpcDispatcher>(params)).await {
+        Ok(res) => res,
+        Err(err) => GrpcResult::Err(status::err_to_status(err).await),
+    };
+    metrics::observe_grpc(&uri_name, Some(&action), &res, started);
+    res
+}
+
+async fn dispatch_grpc<GrpcDispatcher: GrpcRequestDispatcherTrait + Send + Copy + Sync>(mut params: Params) -> GrpcResult<tonic::Response<InvokeResponse>> {
+    let middlewares = middleware::middlewares().await;
+    if let Err(err) = middleware::before(&middlewares, &mut params).await {
+        return GrpcResult::Err(status::err_to_status(err).await);
+    }
+
+    match auth_ict(&mut params).await {
+        Ok(_) => {}
+        Err(err) => {
+            return GrpcResult::Err(status::err_to_status(err).await);
+        }
+    };
+
+    let _permit = match rate_limit::check(&params).await {
+        Ok(permit) => permit,
+        Err(err) => return GrpcResult::Err(status::err_to_status(err).await),
+    };
+
+    if let Err(err) = plugin::run_pre_plugins(&mut params).await {
+        return GrpcResult::Err(status::err_to_status(err).await);
+    }
+
+    if middlewares.is_empty() {
+        return dispatch_idempotent_grpc::<GrpcDispatcher>(params).await;
+    }
+
+    let request_params = params.clone();
+    let mut response = dispatch_idempotent_grpc::<GrpcDispatcher>(params).await?;
+    if let Err(err) = middleware::after_grpc(&middlewares, &request_params, &mut response).await {
+        return GrpcResult::Err(status::err_to_status(err).await);
+    }
+
+    Ok(response)
+}
+
+async fn dispatch_idempotent_grpc<GrpcDispatcher: GrpcRequestDispatcherTrait + Send + Copy + Sync>(
+    params: Params,
+) -> GrpcResult<tonic::Response<InvokeResponse>> {
+    let guard = match idempotency::begin(&params, Protocol::Grpc).await {
+        Ok(Idempotent::Skip) => None,
+        Ok(Idempotent::Replay(record)) => return Ok(idempotency::replay_grpc(record)),
+        Ok(Idempotent::Proceed(guard)) => Some(guard),
+        Err(err) => return GrpcResult::Err(status::err_to_status(err).await),
+    };
+
+    idempotency::finish_grpc(guard, GrpcDispatcher::do_grpc_dispatch(params).await).await
+}
+
+#[tonic::async_trait]
+impl<GrpcDispatcher: GrpcRequestDispatcherTrait + Send + Copy + Sync> AppCallbackHealthCheck for GrpcService<GrpcDispatcher> {
+    async fn health_check(&self, _request: tonic::Request<()>) -> GrpcResult<tonic::Response<HealthCheckResponse>> {
+        if shutdown::is_shutting_down() {
+            return Err(Status::unavailable("shutting down"));
+        }
+        Ok(tonic::Response::new(HealthCheckResponse {}))
     }
 }
 
 pub async fn handle_http<T: Serialize + prost::Message + ModelTrait + Default + DaprBody>(
     http_res: HttpResult<(IfRes<T>, HashMap<String, String>)>,
     params: &Params,
-) -> HttpResult<Response<Either<body::Body, body::BodySt>>> {
-    match http_res {
+) -> HttpResult<Response<Either<body::Body, body::BodySt>>>
+where
+    IfRes<T>: Reflect,
+{
+    match plugin::run_post_plugins(params, http_res).await {
         Ok((if_res, response_header)) => Ok(util::gen_resp_ok(BizResult::from(OK.name()).await?, if_res, response_header, &params).await),
         Err(err) => Ok(util::err_resolve(err).await),
     }
@@ -163,8 +369,11 @@ pub async fn handle_http<T: Serialize + prost::Message + ModelTrait + Default +
 pub async fn handle_grpc<T: prost::Message + ModelTrait + Default + Serialize>(
     http_res: HttpResult<(IfRes<T>, HashMap<String, String>)>,
     params: &Params,
-) -> GrpcResult<tonic::Response<InvokeResponse>> {
-    match http_res {
+) -> GrpcResult<tonic::Response<InvokeResponse>>
+where
+    IfRes<T>: Reflect,
+{
+    match plugin::run_post_plugins(params, http_res).await {
         Ok(if_res) => {
             let mut response = tonic::Response::new(InvokeResponse {
                 content_type: "application/grpc".to_string(),
@@ -174,20 +383,57 @@ pub async fn handle_grpc<T: prost::Message + ModelTrait + Default + Serialize>(
                 }),
                 headers: HashMap::<String, String>::new(),
             });
-            let token_pair = find_response_auth_header(params).await.unwrap();
-            match token_pair.0 {
-                None => {}
-                Some(key) => match token_pair.1 {
-                    None => {}
-                    Some(value) => {
-                        response
-                            .metadata_mut()
-                            .insert(MetadataKey::from_str(key.as_str()).unwrap(), MetadataValue::try_from(value.as_str()).unwrap());
-                    }
-                },
+            response.extensions_mut().insert(BizCode(OK.biz_code()));
+            if let Err(err) = insert_response_auth_metadata(response.metadata_mut(), params).await {
+                return GrpcResult::Err(status::err_to_status(err).await);
             }
             GrpcResult::Ok(response)
         }
-        Err(err) => GrpcResult::Err(Status::internal(err.to_string())),
+        Err(err) => GrpcResult::Err(status::err_to_status(err).await),
     }
 }
+
+/// 原生 gRPC 直接返回 output 的 protobuf 编码, bulk_output 时为 ListValue
+pub async fn handle_native_grpc<T: prost::Message + ModelTrait + Default + Serialize>(
+    http_res: HttpResult<(IfRes<T>, HashMap<String, String>)>,
+    params: &Params,
+) -> GrpcResult<tonic::Response<Vec<u8>>>
+where
+    IfRes<T>: Reflect,
+{
+    match plugin::run_post_plugins(params, http_res).await {
+        Ok(if_res) => {
+            let message = if_res.0.to_message();
+            let output = if message.bulk_output { message.outputs } else { message.output };
+            let mut response = tonic::Response::new(output.map(|any| any.value).unwrap_or_default());
+            response.extensions_mut().insert(BizCode(OK.biz_code()));
+            if let Err(err) = insert_response_auth_metadata(response.metadata_mut(), params).await {
+                return GrpcResult::Err(status::err_to_status(err).await);
+            }
+            GrpcResult::Ok(response)
+        }
+        Err(err) => GrpcResult::Err(status::err_to_status(err).await),
+    }
+}
+
+async fn insert_response_auth_metadata(metadata: &mut MetadataMap, params: &Params) -> HttpResult<()> {
+    let token_pair = find_response_auth_header(params).await?;
+    match token_pair.0 {
+        None => {}
+        Some(key) => match token_pair.1 {
+            None => {}
+            Some(value) => match (MetadataKey::from_str(key.as_str()), MetadataValue::try_from(value.as_str())) {
+                (Ok(key), Ok(value)) => {
+                    metadata.insert(key, value);
+                }
+                _ => {
+                    return Err(util::err_boxed_full_string(
+                        IMPLICIT_RESPONSE_ERROR,
+                        format!("invalid response auth metadata: {}", key),
+                    ))
+                }
+            },
+        },
+    }
+    Ok(())
+}
@@ -0,0 +1,126 @@
+use std::{collections::HashMap, error::Error};
+
+use prost::Message;
+use tonic::{Code, Status};
+use tracing::error;
+
+use crate::{
+    inner_biz_result::*,
+    util::{find_response_error, BizResult},
+};
+
+pub const ERROR_INFO_TYPE_URL: &str = "type.googleapis.com/google.rpc.ErrorInfo";
+pub const ERROR_DOMAIN: &str = "serverless.guide";
+
+/// google.rpc.Status
+#[derive(Clone, PartialEq, Message)]
+pub struct RpcStatus {
+    #[prost(int32, tag = "1")]
+    pub code: i32,
+    #[prost(string, tag = "2")]
+    pub message: String,
+    #[prost(message, repeated, tag = "3")]
+    pub details: Vec<prost_types::Any>,
+}
+
+/// google.rpc.ErrorInfo
+#[derive(Clone, PartialEq, Message)]
+pub struct ErrorInfo {
+    #[prost(string, tag = "1")]
+    pub reason: String,
+    #[prost(string, tag = "2")]
+    pub domain: String,
+    #[prost(map = "string, string", tag = "3")]
+    pub metadata: HashMap<String, String>,
+}
+
+/// 从 gRPC status details 中解析出的业务结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BizStatus {
+    pub code: u32,
+    pub name: String,
+    pub message: String,
+}
+
+/// 按 HTTP 状态码对应 gRPC code
+pub fn grpc_code(status_code: u16) -> Code {
+    match status_code {
+        200..=299 => Code::Ok,
+        400 | 406 | 413 | 415 | 422 => Code::InvalidArgument,
+        401 => Code::Unauthenticated,
+        403 => Code::PermissionDenied,
+        404 => Code::NotFound,
+        405 | 501 => Code::Unimplemented,
+        408 | 504 => Code::DeadlineExceeded,
+        409 => Code::Aborted,
+        412 => Code::FailedPrecondition,
+        429 => Code::ResourceExhausted,
+        499 => Code::Cancelled,
+        503 => Code::Unavailable,
+        400..=499 => Code::FailedPrecondition,
+        _ => Code::Internal,
+    }
+}
+
+/// BizResult 转为带 ErrorInfo details 的 Status
+pub fn biz_status(biz_res: BizResult<'static>, message: Option<&str>) -> Status {
+    let message = match message {
+        None => biz_res.message(),
+        Some(message) => format!("{}: {}", biz_res.message(), message),
+    };
+
+    let mut metadata = HashMap::<String, String>::new();
+    metadata.insert("biz_code".to_string(), biz_res.biz_code().to_string());
+    metadata.insert("message".to_string(), message.clone());
+
+    let error_info = ErrorInfo {
+        reason: biz_res.name(),
+        domain: ERROR_DOMAIN.to_string(),
+        metadata,
+    };
+
+    let code = grpc_code(biz_res.status_code());
+    let rpc_status = RpcStatus {
+        code: code as i32,
+        message: message.clone(),
+        details: vec![prost_types::Any {
+            type_url: ERROR_INFO_TYPE_URL.to_string(),
+            value: error_info.encode_to_vec(),
+        }],
+    };
+
+    Status::with_details(code, message, rpc_status.encode_to_vec().into())
+}
+
+/// 与 err_resolve 相同的规则解析错误, 用于 gRPC 的返回
+pub async fn err_to_status(err: Box<dyn Error + Send + Sync>) -> Status {
+    error!(error = ?err, "handle finish with error");
+
+    let Some(response_err) = find_response_error(err.as_ref()) else {
+        return match BizResult::from(IMPLICIT_RESPONSE_ERROR.name()).await {
+            Ok(implicit_err) => biz_status(implicit_err, Some(&err.to_string())),
+            Err(_) => Status::internal(err.to_string()),
+        };
+    };
+
+    match BizResult::from(response_err.biz_res.clone()).await {
+        Ok(biz_res) => biz_status(biz_res, response_err.message.as_deref()),
+        Err(_) => biz_status(BIZ_RESULT_NOT_FOUND, response_err.message.as_deref()),
+    }
+}
+
+/// 客户端解析 Status 中的 ErrorInfo, 非本 sdk 返回的 Status 为 None
+pub fn decode_biz_status(status: &Status) -> Option<BizStatus> {
+    let rpc_status = RpcStatus::decode(status.details()).ok()?;
+    let any = rpc_status.details.iter().find(|any| any.type_url == ERROR_INFO_TYPE_URL)?;
+    let error_info = ErrorInfo::decode(any.value.as_slice()).ok()?;
+    if error_info.domain != ERROR_DOMAIN {
+        return None;
+    }
+
+    Some(BizStatus {
+        code: error_info.metadata.get("biz_code")?.parse().ok()?,
+        name: error_info.reason,
+        message: error_info.metadata.get("message").cloned().unwrap_or(rpc_status.message),
+    })
+}
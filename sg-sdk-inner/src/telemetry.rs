@@ -0,0 +1,245 @@
+use std::{collections::HashMap, env, str::FromStr, time::Duration};
+
+use hyper::HeaderMap;
+use opentelemetry::{
+    global,
+    propagation::{Extractor, Injector, TextMapCompositePropagator},
+    KeyValue,
+};
+use opentelemetry_otlp::{SpanExporterBuilder, WithExportConfig};
+use opentelemetry_sdk::{
+    propagation::{BaggagePropagator, TraceContextPropagator},
+    runtime, trace, Resource,
+};
+use tonic::metadata::{MetadataKey, MetadataMap, MetadataValue};
+use tracing::{warn, Span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+use crate::{log, HttpResult, FUNC_CONTEXT};
+
+/// 依据 FUNC_CONTEXT.pluginsTracing 初始化 OTLP exporter, 未开启时返回 None
+pub fn init_tracer() -> HttpResult<Option<trace::Tracer>> {
+    global::set_text_map_propagator(TextMapCompositePropagator::new(vec![
+        Box::new(TraceContextPropagator::new()),
+        Box::new(BaggagePropagator::new()),
+    ]));
+
+    if env::var("FUNC_CONTEXT").is_err() {
+        return Ok(None);
+    }
+
+    let Some(tracing_config) = &FUNC_CONTEXT.pluginsTracing else {
+        return Ok(None);
+    };
+    if !tracing_config.enabled.unwrap_or(false) {
+        return Ok(None);
+    }
+
+    let exporter_config = tracing_config.provider.as_ref().and_then(|p| p.exporter.clone()).unwrap_or_default();
+
+    let endpoint = exporter_config.endpoint.clone().ok_or("pluginsTracing.provider.exporter.endpoint not set")?;
+    let timeout = match &exporter_config.timeout {
+        None => Duration::from_secs(10),
+        Some(timeout) => parse_duration(timeout)?,
+    };
+
+    let exporter: SpanExporterBuilder = match exporter_config.protocol.as_deref() {
+        Some("http/protobuf") | Some("http") => {
+            let headers: HashMap<String, String> = exporter_config
+                .headers
+                .as_deref()
+                .unwrap_or("")
+                .split(",")
+                .filter_map(|kv| kv.split_once("="))
+                .map(|(k, v)| (k.trim().to_string(), v.trim().to_string()))
+                .collect();
+
+            opentelemetry_otlp::new_exporter()
+                .http()
+                .with_endpoint(endpoint)
+                .with_timeout(timeout)
+                .with_headers(headers)
+                .into()
+        }
+        _ => {
+            if exporter_config.headers.is_some() {
+                warn!("pluginsTracing exporter headers are only supported with http/protobuf protocol, ignored");
+            }
+
+            let mut exporter = opentelemetry_otlp::new_exporter().tonic().with_endpoint(endpoint).with_timeout(timeout);
+            if let Some("gzip") = exporter_config.compression.as_deref() {
+                exporter = exporter.with_compression(opentelemetry_otlp::Compression::Gzip);
+            }
+            exporter.into()
+        }
+    };
+
+    let mut resource = vec![KeyValue::new("service.name", FUNC_CONTEXT.name.clone().unwrap_or("unknown_function".to_string()))];
+    if let Some(version) = &FUNC_CONTEXT.version {
+        resource.push(KeyValue::new("service.version", version.clone()));
+    }
+    if let Some(tags) = &tracing_config.tags {
+        for (k, v) in tags {
+            resource.push(KeyValue::new(k.clone(), v.clone()));
+        }
+    }
+
+    let tracer = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(exporter)
+        .with_trace_config(trace::config().with_resource(Resource::new(resource)))
+        .install_batch(runtime::Tokio)?;
+
+    Ok(Some(tracer))
+}
+
+pub fn shutdown_tracer() {
+    global::shutdown_tracer_provider();
+}
+
+fn parse_duration(value: &str) -> HttpResult<Duration> {
+    let value = value.trim();
+    if let Some(ms) = value.strip_suffix("ms") {
+        return Ok(Duration::from_millis(u64::from_str(ms)?));
+    }
+    if let Some(s) = value.strip_suffix("s") {
+        return Ok(Duration::from_secs(u64::from_str(s)?));
+    }
+    Ok(Duration::from_secs(u64::from_str(value)?))
+}
+
+pub struct HeaderExtractor<'a>(pub &'a HeaderMap);
+
+impl<'a> Extractor for HeaderExtractor<'a> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|v| v.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|k| k.as_str()).collect()
+    }
+}
+
+pub struct MetadataExtractor<'a>(pub &'a MetadataMap);
+
+impl<'a> Extractor for MetadataExtractor<'a> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|v| v.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0
+            .keys()
+            .filter_map(|k| match k {
+                tonic::metadata::KeyRef::Ascii(k) => Some(k.as_str()),
+                tonic::metadata::KeyRef::Binary(_) => None,
+            })
+            .collect()
+    }
+}
+
+pub struct MetadataInjector<'a>(pub &'a mut MetadataMap);
+
+impl<'a> Injector for MetadataInjector<'a> {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(key), Ok(value)) = (MetadataKey::from_bytes(key.as_bytes()), MetadataValue::try_from(value.as_str())) {
+            self.0.insert(key, value);
+        }
+    }
+}
+
+pub fn set_parent_from(span: &Span, extractor: &dyn Extractor) {
+    let parent = global::get_text_map_propagator(|propagator| propagator.extract(extractor));
+    span.set_parent(parent);
+}
+
+/// 将当前 span 的 trace context 写入 header, 以 traceparent 传递给 sidecar
+pub fn inject_headers(headers: &mut HashMap<String, String>) {
+    let context = Span::current().context();
+    global::get_text_map_propagator(|propagator| propagator.inject_context(&context, headers));
+}
+
+pub fn inject_metadata(metadata: &mut MetadataMap) {
+    let context = Span::current().context();
+    global::get_text_map_propagator(|propagator| propagator.inject_context(&context, &mut MetadataInjector(metadata)));
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct OutboundInterceptor;
+
+impl tonic::service::Interceptor for OutboundInterceptor {
+    fn call(&mut self, mut request: tonic::Request<()>) -> Result<tonic::Request<()>, tonic::Status> {
+        inject_metadata(request.metadata_mut());
+        if let Some(request_id) = log::current_request_id() {
+            if let Ok(value) = MetadataValue::try_from(request_id.as_str()) {
+                request.metadata_mut().insert(log::REQUEST_ID_HEADER, value);
+            }
+        }
+        Ok(request)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use opentelemetry::trace::{TraceContextExt, TracerProvider as _};
+    use opentelemetry_sdk::testing::trace::InMemorySpanExporter;
+    use tracing::info_span;
+    use tracing_subscriber::{layer::SubscriberExt, Registry};
+
+    use super::*;
+
+    /// tracer 只持有 provider 的弱引用, provider 需在测试期间保持存活
+    fn subscriber(exporter: &InMemorySpanExporter) -> (trace::TracerProvider, impl tracing::Subscriber) {
+        let provider = trace::TracerProvider::builder().with_simple_exporter(exporter.clone()).build();
+        let subscriber = Registry::default().with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")));
+        (provider, subscriber)
+    }
+
+    #[test]
+    fn dapr_call_span_is_child_of_request_span() {
+        let exporter = InMemorySpanExporter::default();
+        let (provider, subscriber) = subscriber(&exporter);
+
+        tracing::subscriber::with_default(subscriber, || {
+            info_span!("request", otel.kind = "server").in_scope(|| {
+                info_span!("invoke_service_http").in_scope(|| {});
+            });
+        });
+        // simple exporter 在后台线程导出
+        provider.force_flush();
+
+        let spans = exporter.get_finished_spans().unwrap();
+        let request = spans.iter().find(|span| span.name == "request").unwrap();
+        let dapr_call = spans.iter().find(|span| span.name == "invoke_service_http").unwrap();
+        assert_eq!(dapr_call.span_context.trace_id(), request.span_context.trace_id());
+        assert_eq!(dapr_call.parent_span_id, request.span_context.span_id());
+    }
+
+    #[test]
+    fn traceparent_is_propagated() {
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        let exporter = InMemorySpanExporter::default();
+        let (_provider, subscriber) = subscriber(&exporter);
+
+        tracing::subscriber::with_default(subscriber, || {
+            let request = info_span!("request");
+            let trace_id = request.context().span().span_context().trace_id();
+
+            let mut headers = HashMap::new();
+            request.in_scope(|| inject_headers(&mut headers));
+            let traceparent = headers.get("traceparent").unwrap();
+            assert!(traceparent.contains(&trace_id.to_string()));
+
+            let mut metadata = MetadataMap::new();
+            request.in_scope(|| inject_metadata(&mut metadata));
+            assert_eq!(metadata.get("traceparent").unwrap().to_str().unwrap(), traceparent);
+
+            // 下游以收到的 traceparent 作为 parent, trace 相连
+            let mut header_map = HeaderMap::new();
+            header_map.insert("traceparent", traceparent.parse().unwrap());
+            let downstream = info_span!("downstream");
+            set_parent_from(&downstream, &HeaderExtractor(&header_map));
+            assert_eq!(downstream.context().span().span_context().trace_id(), trace_id);
+        });
+    }
+}
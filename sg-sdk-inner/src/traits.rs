@@ -21,10 +21,58 @@ impl_downcast!(DaprBody);
 
 pub trait HttpRequestDispatcherTrait {
     fn do_http_dispatch(params: Params) -> impl std::future::Future<Output = HttpResult<Response<Either<crate::body::Body, crate::body::BodySt>>>> + Send;
+    /// #[uri_handler] 中声明了 handler 的 uri, 用于启动时的声明检查
+    fn uri_names() -> &'static [&'static str] {
+        &[]
+    }
 }
 
 pub trait GrpcRequestDispatcherTrait {
     fn do_grpc_dispatch(params: Params) -> impl std::future::Future<Output = GrpcResult<tonic::Response<InvokeResponse>>> + Send;
+    fn uri_names() -> &'static [&'static str] {
+        &[]
+    }
+}
+
+/// 原生 gRPC 服务的分发, 请求与响应为 prost 编码的 input/output model
+pub trait NativeGrpcDispatcherTrait {
+    fn do_native_grpc_dispatch(params: Params) -> impl std::future::Future<Output = GrpcResult<tonic::Response<Vec<u8>>>> + Send;
+}
+
+/// before 按注册顺序执行, after 按注册的逆序执行
+#[async_trait::async_trait]
+pub trait Middleware: Send + Sync {
+    async fn before(&self, _params: &mut Params) -> HttpResult<()> {
+        Ok(())
+    }
+
+    async fn after_http(&self, _params: &Params, _response: &mut Response<Either<crate::body::Body, crate::body::BodySt>>) -> HttpResult<()> {
+        Ok(())
+    }
+
+    async fn after_grpc(&self, _params: &Params, _response: &mut tonic::Response<InvokeResponse>) -> HttpResult<()> {
+        Ok(())
+    }
+
+    /// 原生 gRPC 的响应为 prost 编码后的 output model
+    async fn after_native_grpc(&self, _params: &Params, _response: &mut tonic::Response<Vec<u8>>) -> HttpResult<()> {
+        Ok(())
+    }
+}
+
+/// 对应 FUNC_CONTEXT 中的 prePlugins/postPlugins (v1beta2 为 preHooks/postHooks), 以 name 匹配
+#[async_trait::async_trait]
+pub trait Plugin: Send + Sync {
+    fn name(&self) -> &str;
+
+    async fn exec_pre_hook(&self, _params: &mut Params) -> HttpResult<()> {
+        Ok(())
+    }
+
+    /// handler 执行失败时 if_res 为 None
+    async fn exec_post_hook(&self, _params: &Params, _if_res: Option<&mut dyn Reflect>) -> HttpResult<()> {
+        Ok(())
+    }
 }
 
 pub trait EnumConvert {
@@ -36,6 +84,10 @@ pub trait ModelTrait: Any + Send + Sync + Sized + Reflect + Clone {
     where
         Self: Sized;
     fn set_field(&mut self, value: String, field_name: &str) -> Result<&Self, Box<dyn std::error::Error + Sync + Send>>
+    where
+        Self: Sized;
+    /// Vec/HashMap 等无法由字符串解析的字段, 按字段的 serde 属性从 json 值赋值, 不影响其他字段
+    fn set_field_json(&mut self, value: serde_json::Value, field_name: &str) -> Result<&Self, Box<dyn std::error::Error + Sync + Send>>
     where
         Self: Sized;
     fn new() -> Self
@@ -45,6 +97,34 @@ pub trait ModelTrait: Any + Send + Sync + Sized + Reflect + Clone {
     where
         Self: Sized;
     fn get_field_str(&self, field_name: &str) -> Option<String>;
+    /// `#[sensitive]` 标记的字段, 打印日志时打码
+    fn sensitive_fields() -> &'static [&'static str]
+    where
+        Self: Sized,
+    {
+        &[]
+    }
+    /// 字段名, 用于检查 income_param! 中的 target
+    fn field_names() -> &'static [&'static str]
+    where
+        Self: Sized,
+    {
+        &[]
+    }
+    /// 由 Model derive 依据 #[prost(...)] 生成的 proto message 定义
+    fn proto_message() -> &'static str
+    where
+        Self: Sized,
+    {
+        ""
+    }
+    /// 由 Model derive 依据字段类型及 validator 规则生成, 用于 OpenAPI 文档
+    fn json_schema() -> serde_json::Value
+    where
+        Self: Sized,
+    {
+        serde_json::json!({ "type": "object" })
+    }
 }
 
 #[reflect_trait]
@@ -1,16 +1,23 @@
 use crate::{
-    body, daprs::*, inner_biz_result::*, model::*, GrpcResult, HttpResult, DAPR_CONFIG, INCOME_PARAM_MAP, INTERNAL_AUTH_TAG, SKIP_AUTH_IFS, URIS,
-    URI_REGEX_MAP, *,
+    body,
+    codec::{self, Codec},
+    daprs::*,
+    inner_biz_result::*,
+    metrics::BizCode,
+    model::*,
+    openapi,
+    redact::{RedactedModel, RedactedModels},
+    GrpcResult, HttpResult, DAPR_CONFIG, INCOME_PARAM_MAP, INTERNAL_AUTH_TAG, ROUTER, SKIP_AUTH_IFS, URIS, *,
 };
 use chrono::{DateTime, Local};
 use dapr::{
-    appcallback::InvokeRequest,
+    appcallback::{BindingEventResponse, InvokeRequest},
     client::*,
     dapr::dapr::proto::runtime::v1::{
         BulkPublishRequest, ExecuteStateTransactionRequest, GetBulkSecretRequest, GetBulkStateRequest, GetConfigurationRequest, QueryStateRequest,
     },
 };
-use futures_util::{stream::once, TryStreamExt};
+use futures_util::{stream::once, FutureExt, TryStreamExt};
 use http_body::Frame;
 use http_body_util::*;
 use hyper::{
@@ -20,7 +27,6 @@ use hyper::{
 };
 use hyper_util::rt::TokioIo;
 use prost::Message;
-use prost_types::value::Kind;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use sqlparser::{
@@ -33,6 +39,9 @@ use std::{
     collections::HashMap,
     convert::Infallible,
     fmt::{Debug, Display},
+    future::Future,
+    net::SocketAddr,
+    panic::AssertUnwindSafe,
     str::FromStr,
 };
 use tokio::net::TcpStream;
@@ -76,12 +85,18 @@ impl std::error::Error for ResponseError {
     }
 }
 
+pub fn find_response_error<'a>(err: &'a (dyn std::error::Error + Send + Sync + 'static)) -> Option<&'a ResponseError> {
+    if let Some(response_err) = err.downcast_ref::<ResponseError>() {
+        return Some(response_err);
+    }
+    if let Some(not_allowed) = err.downcast_ref::<router::MethodNotAllowed>() {
+        return Some(not_allowed.response_error());
+    }
+    err.downcast_ref::<Box<ResponseError>>().map(|response_err| response_err.as_ref())
+}
+
 pub async fn err_resolve(err: Box<dyn std::error::Error + Send + Sync>) -> Response<Either<body::Body, body::BodySt>> {
-    error!(
-        "============================handle finish with error============================\nend time: {}\n{:#?}",
-        utc_timestamp(),
-        err
-    );
+    error!(end_time = %utc_timestamp(), error = ?err, "handle finish with error");
     if err.is::<ResponseError>() || err.is::<Box<ResponseError>>() {
         let respnse_err;
         if err.is::<ResponseError>() {
@@ -106,13 +121,7 @@ pub async fn err_resolve(err: Box<dyn std::error::Error + Send + Sync>) -> Respo
                     },
                 );
             } else {
-                let implicit_err = match BizResult::from(IMPLICIT_RESPONSE_ERROR.name()).await {
-                    Ok(v) => v,
-                    Err(_) => {
-                        error!("Important!!! IMPLICIT_RESPONSE_ERROR not found");
-                        panic!("Important!!! IMPLICIT_RESPONSE_ERROR not found");
-                    }
-                };
+                let implicit_err = implicit_response_error().await;
                 return gen_resp(
                     implicit_err.status_code(),
                     Res::<String> {
@@ -147,13 +156,7 @@ pub async fn err_resolve(err: Box<dyn std::error::Error + Send + Sync>) -> Respo
             )
         }
     } else {
-        let implicit_err = match BizResult::from(IMPLICIT_RESPONSE_ERROR.name()).await {
-            Ok(v) => v,
-            Err(_) => {
-                error!("Important!!! IMPLICIT_RESPONSE_ERROR not found");
-                panic!("Important!!! IMPLICIT_RESPONSE_ERROR not found");
-            }
-        };
+        let implicit_err = implicit_response_error().await;
         gen_resp(
             implicit_err.status_code(),
             Res::<String> {
@@ -165,6 +168,32 @@ pub async fn err_resolve(err: Box<dyn std::error::Error + Send + Sync>) -> Respo
     }
 }
 
+/// 未注册时使用 sdk 内置的 IMPLICIT_RESPONSE_ERROR
+async fn implicit_response_error() -> BizResult<'static> {
+    match BizResult::from(IMPLICIT_RESPONSE_ERROR.name()).await {
+        Ok(v) => v,
+        Err(_) => {
+            error!("Important!!! IMPLICIT_RESPONSE_ERROR not found");
+            IMPLICIT_RESPONSE_ERROR
+        }
+    }
+}
+
+/// handler 中的 panic 转为 IMPLICIT_RESPONSE_ERROR, 避免单个请求影响整个进程
+pub async fn catch_panic<T, F: Future<Output = T>>(fut: F) -> HttpResult<T> {
+    match AssertUnwindSafe(fut).catch_unwind().await {
+        Ok(res) => Ok(res),
+        Err(panic) => {
+            let message = match panic.downcast_ref::<&str>() {
+                Some(message) => message.to_string(),
+                None => panic.downcast_ref::<String>().cloned().unwrap_or("unknown panic".to_string()),
+            };
+            error!(panic = %message, "handler panicked");
+            Err(err_boxed_full(IMPLICIT_RESPONSE_ERROR, "handler panicked"))
+        }
+    }
+}
+
 pub fn gen_resp<T: Serialize + Display>(status_code: u16, body: Res<T>) -> Response<Either<body::Body, body::BodySt>> {
     let mut response_builder = Response::builder();
 
@@ -175,6 +204,8 @@ pub fn gen_resp<T: Serialize + Display>(status_code: u16, body: Res<T>) -> Respo
     let code = code.unwrap();
     response_builder = response_builder.status(code);
 
+    response_builder = response_builder.extension(BizCode(body.code));
+
     let value = serde_json::to_value(body).unwrap().to_string();
     let resp = response_builder.body(Either::Left(body::bytes(value))).unwrap();
 
@@ -229,22 +260,33 @@ pub async fn gen_resp_ok<T: DaprBody + Serialize + 'static + ModelTrait + prost:
     response_header: HashMap<String, String>,
     params: &Params,
 ) -> Response<Either<body::Body, body::BodySt>> {
+    let is_binary = TypeId::of::<IfRes<T>>() == TypeId::of::<IfRes<BinaryOutPut>>();
+
+    let codec = params.codec;
+
     let mut response_builder = Response::builder();
 
     match response_header.get(header::CONTENT_TYPE.as_str()) {
-        Some(v) => response_builder = response_builder.header(header::CONTENT_TYPE, HeaderValue::from_str(v).unwrap()),
-        None => response_builder = response_builder.header(header::CONTENT_TYPE, HeaderValue::from_str("application/json").unwrap()),
+        Some(v) if is_binary || codec == Codec::Json => match HeaderValue::from_str(v) {
+            Ok(v) => response_builder = response_builder.header(header::CONTENT_TYPE, v),
+            Err(_) => return err_resolve(err_boxed_full_string(IMPLICIT_RESPONSE_ERROR, format!("invalid response content type: {}", v))).await,
+        },
+        _ => response_builder = response_builder.header(header::CONTENT_TYPE, HeaderValue::from_static(codec.content_type())),
     };
 
-    let token_pair = find_response_auth_header(params).await.unwrap();
+    let token_pair = match find_response_auth_header(params).await {
+        Ok(token_pair) => token_pair,
+        Err(err) => return err_resolve(err).await,
+    };
 
     match token_pair.0 {
         None => {}
         Some(key) => match token_pair.1 {
             None => {}
-            Some(value) => {
-                response_builder = response_builder.header(HeaderName::from_str(&key).unwrap(), HeaderValue::from_str(&value).unwrap());
-            }
+            Some(value) => match (HeaderName::from_str(&key), HeaderValue::from_str(&value)) {
+                (Ok(name), Ok(value)) => response_builder = response_builder.header(name, value),
+                _ => return err_resolve(err_boxed_full_string(IMPLICIT_RESPONSE_ERROR, format!("invalid response auth header: {}", key))).await,
+            },
         },
     }
 
@@ -255,7 +297,9 @@ pub async fn gen_resp_ok<T: DaprBody + Serialize + 'static + ModelTrait + prost:
         response_builder = response_builder.status(code.unwrap());
     }
 
-    if TypeId::of::<IfRes<T>>() == TypeId::of::<IfRes<BinaryOutPut>>() {
+    response_builder = response_builder.extension(BizCode(biz_res.biz_code()));
+
+    if is_binary {
         let binary = match result.output {
             None => Box::new(Vec::<u8>::new()),
             Some(binary) => {
@@ -274,24 +318,16 @@ pub async fn gen_resp_ok<T: DaprBody + Serialize + 'static + ModelTrait + prost:
         let stream_body = StreamBody::new(reader_stream.map_ok(Frame::data)).boxed();
         let resp = response_builder.body(Either::Right(body::stream_body(stream_body))).unwrap();
 
-        info!(
-            "============================handle finish OK============================\nend time: {}",
-            utc_timestamp()
-        );
+        info!(end_time = %utc_timestamp(), "handle finish OK");
         resp
     } else {
-        let resp_body = Res::<IfRes<T>> {
-            code: biz_res.biz_code(),
-            message: biz_res.message(),
-            result: Some(result),
+        let resp_body = match codec.encode_res(biz_res, result) {
+            Ok(resp_body) => resp_body,
+            Err(err) => return err_resolve(err).await,
         };
-        let json = serde_json::to_string(&resp_body).unwrap();
-        let resp = response_builder.body(Either::Left(body::bytes(json.as_bytes().to_vec()))).unwrap();
+        let resp = response_builder.body(Either::Left(body::bytes(resp_body))).unwrap();
 
-        info!(
-            "============================handle finish OK============================\nend time: {}",
-            utc_timestamp()
-        );
+        info!(end_time = %utc_timestamp(), "handle finish OK");
         resp
     }
 }
@@ -327,6 +363,16 @@ impl URI {
 
 pub async fn insert_uri(uri: URI) -> HttpResult<()> {
     info!("set uri: {:?}", uri);
+    // input binding 的事件路径先于路由匹配, 同名的路由永远无法访问
+    if let Ok(route) = router::Route::new(uri.clone(), 0) {
+        if let Some(binding_name) = INPUT_BINDINGS.read().await.keys().find(|name| route.is_match(&format!("/{}", name))) {
+            return Err(Box::new(ResponseError {
+                biz_res: format!("uri '{}' conflicts with input binding: {}", uri.name(), binding_name),
+                message: None,
+            }));
+        }
+    }
+
     let mut uris = URIS.write().await;
     match uris.insert(uri.name().to_string(), uri.clone()) {
         None => {}
@@ -338,9 +384,9 @@ pub async fn insert_uri(uri: URI) -> HttpResult<()> {
         }
     };
 
-    info!("set uri regex map: {:?}", uri);
-    let mut uri_regex_map = URI_REGEX_MAP.write().await;
-    uri_regex_map.insert(uri.clone(), regex::Regex::new(uri.path())?);
+    info!("set uri route: {:?}", uri);
+    let mut router = ROUTER.write().await;
+    router.insert(uri.clone())?;
 
     Ok(())
 }
@@ -447,18 +493,66 @@ pub async fn set_skip_auth_uri(uri: URI) -> HttpResult<()> {
     Ok(())
 }
 
-pub async fn uri_match(req_path: &str, req_method: Method) -> HttpResult<URI> {
-    let uri_regex_map = URI_REGEX_MAP.read().await;
-    for (uri, regex) in uri_regex_map.iter() {
-        if regex.is_match(req_path) && uri.method() == req_method {
-            return Ok(uri.to_owned());
+/// 按 application/x-www-form-urlencoded 解析 query: `+` 为空格, 百分号编码按 UTF-8 解码, 没有 `=` 的 key 值为空字符串;
+/// 重复的 key 按出现顺序保留
+pub fn parse_query(query: &str) -> Vec<(String, String)> {
+    query
+        .split("&")
+        .filter(|kv_pair| !kv_pair.is_empty())
+        .map(|kv_pair| {
+            let (k, v) = kv_pair.split_once("=").unwrap_or((kv_pair, ""));
+            (percent_decode(k), percent_decode(v))
+        })
+        .collect()
+}
+
+/// 非法的百分号编码原样保留
+pub fn percent_decode(value: &str) -> String {
+    let hex = |b: u8| (b as char).to_digit(16).map(|d| d as u8);
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::<u8>::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' if i + 2 < bytes.len() => match (hex(bytes[i + 1]), hex(bytes[i + 2])) {
+                (Some(high), Some(low)) => {
+                    decoded.push(high << 4 | low);
+                    i += 3;
+                    continue;
+                }
+                _ => decoded.push(b'%'),
+            },
+            b => decoded.push(b),
         }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// query_param 取每个 key 的第一个值, query_values 保留全部的值
+fn query_params(query: &str) -> (HashMap<String, String>, HashMap<String, Vec<String>>) {
+    let mut query_param = HashMap::<String, String>::new();
+    let mut query_values = HashMap::<String, Vec<String>>::new();
+    for (k, v) in parse_query(query) {
+        query_param.entry(k.clone()).or_insert(v.clone());
+        query_values.entry(k).or_default().push(v);
+    }
+    (query_param, query_values)
+}
+
+/// 按 router 的优先级匹配, 同时返回命名的 path 参数
+pub async fn uri_match(req_path: &str, req_method: Method) -> HttpResult<(URI, HashMap<String, String>)> {
+    let matched = ROUTER.read().await.find(req_path, &req_method);
+    match matched {
+        Some(matched) => Ok(matched),
+        None => Err(router::not_matched(req_path, &req_method).await),
     }
-    Err(err_boxed_full(URI_NOT_MATCH, &format!("uri: {}, method: {}.", req_path, req_method.as_str())))
 }
 
 pub async fn parse_params_grpc(req: tonic::Request<InvokeRequest>) -> GrpcResult<Params> {
     let metadata = &req.metadata().clone();
+    let remote_addr = req.remote_addr().map(|addr| addr.ip());
     let r = &req.into_inner();
 
     let Some(http_extension) = &r.http_extension else {
@@ -472,9 +566,9 @@ pub async fn parse_params_grpc(req: tonic::Request<InvokeRequest>) -> GrpcResult
 
     let path = &r.method;
 
-    let uri = uri_match(path, http_method).await;
-    let Ok(uri) = uri else {
-        return Err(Status::failed_precondition("uri not match."));
+    let (uri, named_path_params) = match uri_match(path, http_method).await {
+        Ok(matched) => matched,
+        Err(err) => return Err(status::err_to_status(err).await),
     };
 
     let mut uri_path_params = HashMap::<u8, String>::new();
@@ -485,26 +579,23 @@ pub async fn parse_params_grpc(req: tonic::Request<InvokeRequest>) -> GrpcResult
 
     let mut headers = HashMap::<String, String>::new();
     for (k, v) in metadata.clone().into_headers().iter() {
-        headers.insert(k.to_string(), v.to_str().unwrap().to_string());
+        headers.insert(k.to_string(), String::from_utf8_lossy(v.as_bytes()).to_string());
     }
 
-    if !headers.contains_key("Content-Type") {
-        headers.insert("Content-Type".to_string(), r.content_type.clone().to_string());
-    }
+    // metadata 中的 content-type 为传输层的 application/grpc, data 的类型以 InvokeRequest.content_type 为准
+    headers.insert(header::CONTENT_TYPE.to_string(), r.content_type.clone());
 
-    let mut uri_query_params = HashMap::<String, String>::new();
-    let query = &http_extension.querystring;
-    let querys: Vec<&str> = query.split("&").collect();
-    for kv_pair in querys {
-        let kvs: Vec<&str> = kv_pair.split("=").collect();
-        uri_query_params.insert(kvs[0].to_string(), kvs[1].to_string());
-    }
+    let (uri_query_params, uri_query_values) = query_params(&http_extension.querystring);
 
     let mut params: Params = Default::default();
     params.uri = uri.name().to_string();
     params.header = headers;
+    params.request_id = log::request_id(params.header.get(log::REQUEST_ID_HEADER).map(|v| v.as_str()));
     params.query_param = uri_query_params;
+    params.query_values = uri_query_values;
     params.path_param = uri_path_params;
+    params.named_path_param = named_path_params;
+    params.remote_addr = remote_addr;
     params.if_info = IfInfo {
         action: uri.action().clone(),
         bulk_input: uri.bulk_input().clone(),
@@ -521,23 +612,70 @@ pub async fn parse_params_grpc(req: tonic::Request<InvokeRequest>) -> GrpcResult
     Ok(params)
 }
 
-pub async fn parse_params(req: Request<Incoming>) -> HttpResult<Params> {
-    let uri = uri_match(req.uri().path(), req.method().to_owned()).await?;
+/// 原生 gRPC 请求以方法名匹配 URI, body 按 protobuf 解码, 没有 path/query 参数
+pub async fn parse_params_native(uri_name: &str, req: tonic::Request<Vec<u8>>) -> GrpcResult<Params> {
+    let uri = URIS.read().await.get(uri_name).cloned();
+    let Some(uri) = uri else {
+        return Err(status::biz_status(URI_NOT_MATCH, Some(&format!("grpc method: {}", uri_name))));
+    };
 
     let mut headers = HashMap::<String, String>::new();
-    for (k, v) in req.headers().into_iter() {
-        headers.insert(k.to_string(), v.to_str().unwrap().to_string());
+    for (k, v) in req.metadata().clone().into_headers().iter() {
+        if let Ok(v) = v.to_str() {
+            headers.insert(k.to_string(), v.to_string());
+        }
     }
+    headers.insert(header::CONTENT_TYPE.to_string(), codec::APPLICATION_PROTOBUF.to_string());
 
-    let mut uri_query_params = HashMap::<String, String>::new();
-    let query = req.uri().query();
-    if let Some(query) = query {
-        let querys: Vec<&str> = query.split("&").collect();
-        for kv_pair in querys {
-            let kvs: Vec<&str> = kv_pair.split("=").collect();
-            uri_query_params.insert(kvs[0].to_string(), kvs[1].to_string());
+    let mut params: Params = Default::default();
+    params.uri = uri.name().to_string();
+    params.header = headers;
+    params.request_id = log::request_id(params.header.get(log::REQUEST_ID_HEADER).map(|v| v.as_str()));
+    params.remote_addr = req.remote_addr().map(|addr| addr.ip());
+    params.if_info = IfInfo {
+        action: uri.action().clone(),
+        bulk_input: uri.bulk_input().clone(),
+        bulk_output: uri.bulk_output().clone(),
+    };
+
+    let body = req.into_inner();
+    if !body.is_empty() {
+        params.body = Some(body);
+    }
+
+    info!(start_time = %utc_timestamp(), params = ?params, "accept param");
+
+    Ok(params)
+}
+
+pub async fn collect_body(body: body::ReqBody) -> HttpResult<Vec<u8>> {
+    match body.collect().await {
+        Ok(collected) => Ok(collected.to_bytes().to_vec()),
+        Err(err) => {
+            if err.is::<LengthLimitError>() {
+                return Err(err_boxed(REQUEST_BODY_TOO_LARGE));
+            }
+            Err(err)
         }
     }
+}
+
+pub async fn parse_params(req: Request<body::ReqBody>) -> HttpResult<Params> {
+    let (uri, named_path_params) = uri_match(req.uri().path(), req.method().to_owned()).await?;
+
+    let mut headers = HashMap::<String, String>::new();
+    for (k, v) in req.headers().into_iter() {
+        headers.insert(k.to_string(), String::from_utf8_lossy(v.as_bytes()).to_string());
+    }
+
+    // 分发前确定响应编码, 不支持的 Accept 在 handler 产生副作用之前返回 NOT_ACCEPTABLE
+    let codec = match Codec::negotiate(headers.get(header::ACCEPT.as_str()).map(|v| v.as_str())) {
+        Ok(codec) => codec,
+        Err(_) if openapi::is_binary_output(uri.name()).await => Codec::Json,
+        Err(err) => return Err(err),
+    };
+
+    let (uri_query_params, uri_query_values) = query_params(req.uri().query().unwrap_or_default());
 
     let mut uri_path_params = HashMap::<u8, String>::new();
     let path = req.uri().path();
@@ -555,19 +693,26 @@ pub async fn parse_params(req: Request<Incoming>) -> HttpResult<Params> {
         bulk_output: uri.bulk_output().clone(),
     };
 
+    params.request_id = log::request_id(headers.get(log::REQUEST_ID_HEADER).map(|v| v.as_str()));
+    params.codec = codec;
+    params.remote_addr = req.extensions().get::<SocketAddr>().map(|addr| addr.ip());
+
     if headers.len() > 0 {
         params.header = headers;
     }
 
     if uri_query_params.len() > 0 {
         params.query_param = uri_query_params;
+        params.query_values = uri_query_values;
     }
 
     if uri_path_params.len() > 0 {
         params.path_param = uri_path_params;
     }
 
-    let body_bytes = req.collect().await?.to_bytes().to_vec();
+    params.named_path_param = named_path_params;
+
+    let body_bytes = collect_body(req.into_body()).await?;
 
     if body_bytes.is_empty() {
         params.body = None;
@@ -575,21 +720,66 @@ pub async fn parse_params(req: Request<Incoming>) -> HttpResult<Params> {
         params.body = Some(body_bytes);
     }
 
-    info!(
-        "============================accept param============================\nstart time: {}\n{:?}",
-        utc_timestamp(),
-        params
-    );
+    info!(start_time = %utc_timestamp(), params = ?params, "accept param");
 
     Ok(params)
 }
 
-fn de_bytes_slice<T: for<'de> Deserialize<'de>>(bytes: &[u8]) -> HttpResult<T> {
-    let model = serde_json::from_slice::<T>(&bytes[..]);
-    if let Err(err) = model {
-        return Err(err_boxed_full(CONVERT_TO_MODEL_ERROR, &err.to_string()));
-    };
-    Ok(model.unwrap())
+async fn parse_form_data(bytes: Vec<u8>, content_type: &str) -> HttpResult<Vec<FormDataParam>> {
+    let media_type = content_type.parse::<mime::Mime>()?;
+    let (_, boundary) = media_type
+        .params()
+        .find(|(k, _)| k.as_str() == mime::BOUNDARY.as_str())
+        .ok_or("boundary not found")?;
+
+    let stream = once(async move { Result::<Bytes, Infallible>::Ok(Bytes::from(bytes)) });
+    let mut multipart = multer::Multipart::new(stream, boundary.as_str());
+    let mut form_data_params = Vec::<FormDataParam>::new();
+
+    while let Some(mut field) = multipart.next_field().await? {
+        while let Some(chunk) = field.chunk().await? {
+            let name = field.name().map(|e| e.to_string());
+            let file_name = field.file_name().map(|e| e.to_string());
+            form_data_params.push(FormDataParam {
+                field_name: name,
+                file_name: file_name,
+                data: Some(Box::new(chunk.to_vec())),
+            });
+        }
+    }
+
+    Ok(form_data_params)
+}
+
+/// Vec/HashMap 类型的字段无法通过 set_field 赋值, 只反序列化该字段; 字符串无法反序列化时再按数字/布尔值重试
+fn set_field_json<I: ModelTrait>(input_param: &mut I, field_name: &str, value: serde_json::Value) -> HttpResult<()> {
+    if input_param.set_field_json(value.clone(), field_name).is_ok() || input_param.set_field_json(coerce_json_value(value), field_name).is_ok() {
+        return Ok(());
+    }
+
+    Err(err_boxed_full(
+        CONVERT_TO_MODEL_ERROR,
+        &format!("can not parse query parameter into field {field_name}"),
+    ))
+}
+
+fn coerce_json_value(value: serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::String(v) => {
+            if let Ok(n) = v.parse::<i64>() {
+                json!(n)
+            } else if let Ok(n) = v.parse::<f64>() {
+                json!(n)
+            } else if let Ok(b) = v.parse::<bool>() {
+                json!(b)
+            } else {
+                serde_json::Value::String(v)
+            }
+        }
+        serde_json::Value::Array(values) => serde_json::Value::Array(values.into_iter().map(coerce_json_value).collect()),
+        serde_json::Value::Object(map) => serde_json::Value::Object(map.into_iter().map(|(k, v)| (k, coerce_json_value(v))).collect()),
+        value => value,
+    }
 }
 
 pub fn set_input_param<I: for<'de> Deserialize<'de> + ModelTrait + prost::Message + Default + Serialize>(
@@ -619,41 +809,58 @@ pub fn set_input_param<I: for<'de> Deserialize<'de> + ModelTrait + prost::Messag
             }
 
             ParamFrom::Path => {
-                let path_pos = name.parse::<u8>();
-                if let Err(err) = path_pos {
-                    return Err(err_boxed_full(PARAM_MAP_PARSE_ERROR, &err.to_string()));
-                }
-
-                if params.path_param.is_empty() {
-                    return Err(err_boxed(PATH_PARAM_NOT_EXIST));
-                }
+                // 数字为 path segment 的位置, 否则为路由模板中的参数名
+                let value = match name.parse::<u8>() {
+                    Ok(path_pos) => params.path_param.get(&path_pos),
+                    Err(_) => params.named_path_param.get(name),
+                };
 
                 if param_def.required {
-                    let Some(value) = params.path_param.get(&path_pos.unwrap()) else {
+                    let Some(value) = value else {
                         return Err(err_boxed_full(PATH_PARAM_NOT_EXIST, &format!("path param {name} not found")));
                     };
                     input_param.set_field(value.to_owned(), target_name.as_str())?;
                 } else {
-                    if let Some(value) = params.path_param.get(&path_pos.unwrap()) {
+                    if let Some(value) = value {
                         input_param.set_field(value.to_owned(), target_name.as_str())?;
                     };
                 }
             }
 
             ParamFrom::Query => {
-                if !params.query_param.is_empty() {
-                    return Err(err_boxed(QUERY_PARAM_NOT_EXIST));
-                }
+                let value = match param_def.param_type {
+                    // ?id=1&id=2 或 ?id[]=1&id[]=2
+                    ParamType::Vec => params
+                        .query_values
+                        .get(name)
+                        .or(params.query_values.get(&format!("{name}[]")))
+                        .map(|values| serde_json::Value::Array(values.iter().map(|v| serde_json::Value::String(v.to_owned())).collect())),
+                    // ?filter[name]=x&filter[age]=1, 重复的 key 与 query_param 相同取第一个值
+                    ParamType::HashMap => {
+                        let prefix = format!("{name}[");
+                        let map = params
+                            .query_values
+                            .iter()
+                            .filter_map(|(k, values)| {
+                                let key = k.strip_prefix(&prefix)?.strip_suffix("]")?;
+                                Some((key.to_string(), serde_json::Value::String(values.first()?.to_owned())))
+                            })
+                            .collect::<serde_json::Map<String, serde_json::Value>>();
+                        match map.is_empty() {
+                            true => None,
+                            false => Some(serde_json::Value::Object(map)),
+                        }
+                    }
+                    _ => params.query_param.get(name).map(|v| serde_json::Value::String(v.to_owned())),
+                };
 
-                if param_def.required {
-                    let Some(value) = params.query_param.get(name) else {
-                        return Err(err_boxed_full(PATH_PARAM_NOT_EXIST, &format!("query parameter {name} not found")));
-                    };
-                    input_param.set_field(value.to_owned(), target_name.as_str())?;
-                } else {
-                    if let Some(value) = params.query_param.get(name) {
-                        input_param.set_field(value.to_owned(), target_name.as_str())?;
-                    };
+                match value {
+                    None if param_def.required => {
+                        return Err(err_boxed_full(QUERY_PARAM_NOT_EXIST, &format!("query parameter {name} not found")));
+                    }
+                    None => {}
+                    Some(serde_json::Value::String(value)) => input_param.set_field(value, target_name.as_str())?,
+                    Some(value) => set_field_json(input_param, target_name.as_str(), value)?,
                 }
             }
 
@@ -716,105 +923,29 @@ pub async fn params_to_model<
     let mut input_params;
     let mut form_data = None;
 
+    let content_type = codec::content_type(&params.header);
+    let is_form_data = content_type.map(|v| v.starts_with("multipart/form-data")).unwrap_or(false);
+    let codec = Codec::from_content_type(content_type);
+
     if params.if_info.bulk_input {
         input_param = Default::default();
-        input_params = if let None = params.body {
-            Default::default()
-        } else {
-            let bytes = params.body.clone().unwrap();
-            match params.header.get_key_value("Content-Type") {
-                None => de_bytes_slice::<Vec<I>>(&bytes[..])?,
-                Some((_, value)) => {
-                    if value == "application/grpc" || value == "application/grpc+proto" {
-                        let any = prost_types::Any::decode(&bytes[..])?;
-                        let list_value = prost_types::ListValue::decode(&any.value[..])?;
-                        let mut prost_inputs = Vec::<I>::new();
-                        for ele in list_value.values {
-                            match ele.kind {
-                                None => {}
-                                Some(kind) => match kind {
-                                    Kind::StructValue(struct_value) => {
-                                        let struct_vec = struct_value.encode_to_vec();
-                                        prost_inputs.push(I::decode(&struct_vec[..])?)
-                                    }
-                                    _ => {}
-                                },
-                            }
-                        }
-                        prost_inputs
-                    } else if value.starts_with("multipart/form-data") {
-                        let media_type = value.parse::<mime::Mime>()?;
-                        let (_, boundary) = media_type
-                            .params()
-                            .find(|(k, _)| k.as_str() == mime::BOUNDARY.as_str())
-                            .ok_or("boundary not found")?;
-
-                        let stream = once(async move { Result::<Bytes, Infallible>::Ok(Bytes::from(bytes)) });
-                        let mut multipart = multer::Multipart::new(stream, boundary.as_str());
-                        let mut form_data_params = Vec::<FormDataParam>::new();
-
-                        while let Some(mut field) = multipart.next_field().await? {
-                            while let Some(chunk) = field.chunk().await? {
-                                let name = field.name().map(|e| e.to_string());
-                                let file_name = field.file_name().map(|e| e.to_string());
-                                form_data_params.push(FormDataParam {
-                                    field_name: name,
-                                    file_name: file_name,
-                                    data: Some(Box::new(chunk.to_vec())),
-                                });
-                            }
-                        }
-
-                        form_data = Some(form_data_params);
-                        Default::default()
-                    } else {
-                        de_bytes_slice::<Vec<I>>(&bytes[..])?
-                    }
-                }
+        input_params = match &params.body {
+            None => Default::default(),
+            Some(bytes) if is_form_data => {
+                form_data = Some(parse_form_data(bytes.clone(), content_type.unwrap_or_default()).await?);
+                Default::default()
             }
+            Some(bytes) => codec.decode_list::<I>(&bytes[..])?,
         };
     } else {
         input_params = Default::default();
-        input_param = if let None = params.body {
-            Default::default()
-        } else {
-            let bytes = params.body.clone().unwrap();
-            match params.header.get_key_value("Content-Type") {
-                None => de_bytes_slice::<I>(&bytes[..])?,
-                Some((_, value)) => {
-                    if value == "application/grpc" || value == "application/grpc+proto" {
-                        let any = prost_types::Any::decode(&bytes[..])?;
-                        I::decode(&any.value[..])?
-                    } else if value.starts_with("multipart/form-data") {
-                        let media_type = value.parse::<mime::Mime>()?;
-                        let (_, boundary) = media_type
-                            .params()
-                            .find(|(k, _)| k.as_str() == mime::BOUNDARY.as_str())
-                            .ok_or("boundary not found")?;
-
-                        let stream = once(async move { Result::<Bytes, Infallible>::Ok(Bytes::from(bytes)) });
-                        let mut multipart = multer::Multipart::new(stream, boundary.as_str());
-                        let mut form_data_params = Vec::<FormDataParam>::new();
-
-                        while let Some(mut field) = multipart.next_field().await? {
-                            while let Some(chunk) = field.chunk().await? {
-                                let name = field.name().map(|e| e.to_string());
-                                let file_name = field.file_name().map(|e| e.to_string());
-                                form_data_params.push(FormDataParam {
-                                    field_name: name,
-                                    file_name: file_name,
-                                    data: Some(Box::new(chunk.to_vec())),
-                                });
-                            }
-                        }
-
-                        form_data = Some(form_data_params);
-                        Default::default()
-                    } else {
-                        de_bytes_slice::<I>(&bytes[..])?
-                    }
-                }
+        input_param = match &params.body {
+            None => Default::default(),
+            Some(bytes) if is_form_data => {
+                form_data = Some(parse_form_data(bytes.clone(), content_type.unwrap_or_default()).await?);
+                Default::default()
             }
+            Some(bytes) => codec.decode::<I>(&bytes[..])?,
         };
     }
 
@@ -827,11 +958,12 @@ pub async fn params_to_model<
     let exec = HashMap::<String, (DaprRequest, DaprResponse, Option<Vec<Box<dyn DaprBody>>>)>::new();
 
     if !income_param_exist {
-        debug!("input_param model: {:?}", input_param);
-        debug!("input_params model: {:?}", input_params);
+        debug!("input_param model: {:?}", RedactedModel(&input_param));
+        debug!("input_params model: {:?}", RedactedModels(&input_params));
 
         return Ok(ContextWrapper {
             saga_id,
+            request_id: params.request_id.clone(),
             uri_name: params.uri.clone(),
             if_info: params.if_info.clone(),
             input: input_param,
@@ -842,11 +974,14 @@ pub async fn params_to_model<
             exec_name: None,
             header: params.header.clone(),
             path_param: params.path_param.clone(),
+            named_path_param: params.named_path_param.clone(),
             query_param: params.query_param.clone(),
+            query_values: params.query_values.clone(),
             page_info: None,
             inner_context: Default::default(),
             form_data: form_data,
             response_header: HashMap::new(),
+            binding_response: Default::default(),
         });
     }
 
@@ -860,11 +995,12 @@ pub async fn params_to_model<
         set_input_param(param_map, &params, &mut input_param, &form_data)?;
     }
 
-    debug!("input_param model: {:?}", input_param);
-    debug!("input_params model: {:?}", &input_params);
+    debug!("input_param model: {:?}", RedactedModel(&input_param));
+    debug!("input_params model: {:?}", RedactedModels(&input_params));
 
     Ok(ContextWrapper {
         saga_id,
+        request_id: params.request_id.clone(),
         uri_name: params.uri.clone(),
         if_info: params.if_info.clone(),
         input: input_param,
@@ -875,11 +1011,14 @@ pub async fn params_to_model<
         exec_name: None,
         header: params.header.clone(),
         path_param: params.path_param.clone(),
+        named_path_param: params.named_path_param.clone(),
         query_param: params.query_param.clone(),
+        query_values: params.query_values.clone(),
         page_info: None,
         inner_context: Default::default(),
         form_data: form_data,
         response_header: HashMap::new(),
+        binding_response: Default::default(),
     })
 }
 
@@ -909,13 +1048,10 @@ pub fn utc_timestamp() -> DateTime<Local> {
 pub async fn res<I: ModelTrait + Default + prost::Message, O: ModelTrait + Validator + prost::Message + std::default::Default, C: Clone>(
     context: ContextWrapper<I, O, C>,
 ) -> HttpResult<(IfRes<O>, HashMap<String, String>)> {
-    let uris = URIS.read().await;
-    let uri = uris.get(&context.uri_name).unwrap();
-
     let mut if_res: IfRes<O> = Default::default();
     if_res.saga_id = context.saga_id;
-    if_res.uri_name = Some(uri.name().to_string());
-    if_res.action = Some(uri.action().to_i32());
+    if_res.uri_name = Some(context.uri_name.clone());
+    if_res.action = Some(context.if_info.action.to_i32());
     if_res.bulk_output = context.if_info.bulk_output;
     if context.if_info.bulk_output {
         if_res.outputs = context.outputs;
@@ -928,6 +1064,22 @@ pub async fn res<I: ModelTrait + Default + prost::Message, O: ModelTrait + Valid
     Ok((if_res, context.response_header))
 }
 
+pub async fn binding_res<I: ModelTrait + Default + prost::Message, O: ModelTrait + Serialize + prost::Message + std::default::Default, C: Clone>(
+    context: ContextWrapper<I, O, C>,
+) -> HttpResult<BindingEventResponse> {
+    let mut binding_response = context.binding_response;
+
+    if binding_response.data.is_empty() {
+        binding_response.data = if context.if_info.bulk_output {
+            serde_json::to_vec(&context.outputs)?
+        } else {
+            serde_json::to_vec(&context.output)?
+        };
+    }
+
+    Ok(binding_response)
+}
+
 pub async fn hyper_request(
     url: String,
     http_method: Method,
@@ -954,10 +1106,13 @@ pub async fn hyper_request(
         .uri(hyper_url.clone())
         .header("Content-Type", "application/json");
 
-    if let Some(headers) = headers {
-        for (key, value) in headers {
-            builder = builder.header(&key, &value);
-        }
+    let mut headers = headers.unwrap_or_default();
+    telemetry::inject_headers(&mut headers);
+    if let Some(request_id) = log::current_request_id() {
+        headers.entry(log::REQUEST_ID_HEADER.to_string()).or_insert(request_id);
+    }
+    for (key, value) in headers {
+        builder = builder.header(&key, &value);
     }
 
     let req = if let None = body {
@@ -1778,27 +1933,27 @@ pub async fn find_response_auth_header(params: &Params) -> HttpResult<(Option<St
         ));
     } else if params.header.contains_key(AuthHeader::XSGAuthBasic.lower_case_value()) {
     } else if params.header.contains_key(AuthHeader::XSGAuthBasic.upper_case_value()) {
-        todo!();
+        return Ok((None, None));
     } else if params.header.contains_key(AuthHeader::XSGAuthOAuth2.lower_case_value()) {
-        todo!();
+        return Ok((None, None));
     } else if params.header.contains_key(AuthHeader::XSGAuthOAuth2.upper_case_value()) {
-        todo!();
+        return Ok((None, None));
     } else if params.header.contains_key(AuthHeader::XSGAuthAksk.lower_case_value()) {
-        todo!();
+        return Ok((None, None));
     } else if params.header.contains_key(AuthHeader::XSGAuthAksk.upper_case_value()) {
-        todo!();
+        return Ok((None, None));
     } else if params.header.contains_key(AuthHeader::XSGAuthApiKey.lower_case_value()) {
-        todo!();
+        return Ok((None, None));
     } else if params.header.contains_key(AuthHeader::XSGAuthApiKey.upper_case_value()) {
-        todo!();
+        return Ok((None, None));
     } else if params.header.contains_key(AuthHeader::XSGAuthDigestAuth.lower_case_value()) {
-        todo!();
+        return Ok((None, None));
     } else if params.header.contains_key(AuthHeader::XSGAuthDigestAuth.upper_case_value()) {
-        todo!();
+        return Ok((None, None));
     } else if params.header.contains_key(AuthHeader::XSGAuthOIDC.lower_case_value()) {
-        todo!();
+        return Ok((None, None));
     } else if params.header.contains_key(AuthHeader::XSGAuthOIDC.upper_case_value()) {
-        todo!();
+        return Ok((None, None));
     } else {
         return Err(err_boxed_full(AUTH_ERROR, "at least one auth type needed"));
     }
@@ -1877,29 +2032,29 @@ pub async fn auth_ict(params: &mut Params) -> HttpResult<()> {
             }
         }
     } else if params.header.contains_key(AuthHeader::XSGAuthBasic.lower_case_value()) {
-        todo!();
+        return Err(err_boxed_full(AUTH_ERROR, "basic auth not supported"));
     } else if params.header.contains_key(AuthHeader::XSGAuthBasic.upper_case_value()) {
-        todo!();
+        return Err(err_boxed_full(AUTH_ERROR, "basic auth not supported"));
     } else if params.header.contains_key(AuthHeader::XSGAuthOAuth2.lower_case_value()) {
-        todo!();
+        return Err(err_boxed_full(AUTH_ERROR, "oauth2 auth not supported"));
     } else if params.header.contains_key(AuthHeader::XSGAuthOAuth2.upper_case_value()) {
-        todo!();
+        return Err(err_boxed_full(AUTH_ERROR, "oauth2 auth not supported"));
     } else if params.header.contains_key(AuthHeader::XSGAuthAksk.lower_case_value()) {
-        todo!();
+        return Err(err_boxed_full(AUTH_ERROR, "aksk auth not supported"));
     } else if params.header.contains_key(AuthHeader::XSGAuthAksk.upper_case_value()) {
-        todo!();
+        return Err(err_boxed_full(AUTH_ERROR, "aksk auth not supported"));
     } else if params.header.contains_key(AuthHeader::XSGAuthApiKey.lower_case_value()) {
-        todo!();
+        return Err(err_boxed_full(AUTH_ERROR, "api key auth not supported"));
     } else if params.header.contains_key(AuthHeader::XSGAuthApiKey.upper_case_value()) {
-        todo!();
+        return Err(err_boxed_full(AUTH_ERROR, "api key auth not supported"));
     } else if params.header.contains_key(AuthHeader::XSGAuthDigestAuth.lower_case_value()) {
-        todo!();
+        return Err(err_boxed_full(AUTH_ERROR, "digest auth not supported"));
     } else if params.header.contains_key(AuthHeader::XSGAuthDigestAuth.upper_case_value()) {
-        todo!();
+        return Err(err_boxed_full(AUTH_ERROR, "digest auth not supported"));
     } else if params.header.contains_key(AuthHeader::XSGAuthOIDC.lower_case_value()) {
-        todo!();
+        return Err(err_boxed_full(AUTH_ERROR, "oidc auth not supported"));
     } else if params.header.contains_key(AuthHeader::XSGAuthOIDC.upper_case_value()) {
-        todo!();
+        return Err(err_boxed_full(AUTH_ERROR, "oidc auth not supported"));
     } else {
         return Err(err_boxed_full(AUTH_ERROR, "at least one auth type needed"));
     }
@@ -1993,11 +2148,108 @@ pub fn prost_to_serde_json(x: prost_types::Value) -> serde_json::Value {
         Some(x) => match x {
             NullValue(_) => Null,
             BoolValue(v) => Bool(v),
-            NumberValue(n) => Number(serde_json::Number::from_f64(n).unwrap()),
+            NumberValue(n) => serde_json::Number::from_f64(n).map(Number).unwrap_or(Null),
             StringValue(s) => String(s),
             ListValue(lst) => Array(lst.values.into_iter().map(prost_to_serde_json).collect()),
             StructValue(v) => Object(v.fields.into_iter().map(|(k, v)| (k, prost_to_serde_json(v))).collect()),
         },
-        None => panic!("todo"),
+        None => Null,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy_reflect::{GetField, Reflect};
+    use sg_sdk_macro::Model;
+
+    use super::*;
+
+    #[derive(Clone, PartialEq, Serialize, Deserialize, Reflect, Model, prost::Message)]
+    struct QueryInput {
+        #[prost(int64, repeated, tag = "1")]
+        ids: Vec<i64>,
+        #[prost(map = "string, string", tag = "2")]
+        filter: HashMap<String, String>,
+        #[prost(string, optional, tag = "3")]
+        name: Option<String>,
+    }
+
+    fn query_input(query: &str) -> HttpResult<QueryInput> {
+        let param_def = |name: &str, param_type: ParamType| IncomeParamDef {
+            name: name.to_string(),
+            required: false,
+            from: ParamFrom::Query,
+            param_type,
+        };
+        let param_map = ExtraParamMap {
+            params: HashMap::from([
+                ("ids".to_string(), param_def("id", ParamType::Vec)),
+                ("filter".to_string(), param_def("filter", ParamType::HashMap)),
+                ("name".to_string(), param_def("name", ParamType::String)),
+            ]),
+        };
+
+        let (query_param, query_values) = query_params(query);
+        let params = Params {
+            query_param,
+            query_values,
+            ..Default::default()
+        };
+        let mut input = QueryInput::default();
+        set_input_param(&param_map, &params, &mut input, &None)?;
+        Ok(input)
+    }
+
+    #[test]
+    fn key_without_value_is_empty_string() {
+        assert_eq!(parse_query("debug"), vec![("debug".to_string(), String::new())]);
+        assert_eq!(
+            parse_query("debug&id=1&"),
+            vec![("debug".to_string(), String::new()), ("id".to_string(), "1".to_string())]
+        );
+    }
+
+    #[test]
+    fn plus_is_space_and_percent_is_utf8() {
+        assert_eq!(percent_decode("a+b"), "a b");
+        assert_eq!(percent_decode("a%2Bb"), "a+b");
+        assert_eq!(percent_decode("%E4%B8%AD"), "中");
+        assert_eq!(parse_query("name=hello+world"), vec![("name".to_string(), "hello world".to_string())]);
+    }
+
+    #[test]
+    fn invalid_percent_encoding_is_kept() {
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz"), "%zz");
+        assert_eq!(percent_decode("a%4"), "a%4");
+    }
+
+    #[test]
+    fn repeated_key_keeps_first_value_and_all_values() {
+        let (query_param, query_values) = query_params("id=1&id=2");
+        assert_eq!(query_param.get("id").map(|v| v.as_str()), Some("1"));
+        assert_eq!(query_values.get("id"), Some(&vec!["1".to_string(), "2".to_string()]));
+    }
+
+    #[test]
+    fn bracket_keys_are_decoded() {
+        let expected = vec![("filter[name]".to_string(), "x".to_string())];
+        assert_eq!(parse_query("filter[name]=x"), expected);
+        assert_eq!(parse_query("filter%5Bname%5D=x"), expected);
+    }
+
+    #[test]
+    fn repeated_query_keys_fill_vec_field() {
+        assert_eq!(query_input("id=1&id=2").unwrap().ids, vec![1, 2]);
+        assert_eq!(query_input("id[]=3&id[]=4").unwrap().ids, vec![3, 4]);
+        assert!(query_input("id=a").is_err());
+    }
+
+    #[test]
+    fn repeated_query_keys_keep_first_value_for_map_and_scalar_fields() {
+        let input = query_input("filter[name]=x&filter[name]=y&filter[age]=2&name=a&name=b").unwrap();
+        assert_eq!(input.filter.get("name").map(|v| v.as_str()), Some("x"));
+        assert_eq!(input.filter.get("age").map(|v| v.as_str()), Some("2"));
+        assert_eq!(input.name.as_deref(), Some("a"));
     }
 }
@@ -22,7 +22,7 @@ pub fn derive_model_validator(input: proc_macro::TokenStream) -> proc_macro::Tok
     expanded.into()
 }
 
-#[proc_macro_derive(Model)]
+#[proc_macro_derive(Model, attributes(sensitive))]
 pub fn derive_model(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
     let name = input.ident;
@@ -45,6 +45,13 @@ pub fn derive_model(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
         }
     });
 
+    // #[sensitive] 标记的字段在日志中打码
+    let sensitive_fields = data
+        .named
+        .iter()
+        .filter(|field| field.attrs.iter().any(|attr| attr.path().is_ident("sensitive")))
+        .filter_map(|field| field.ident.as_ref().map(|ident| ident.to_string()));
+
     let target_type = vec![
         "i8", "u8", "i16", "u16", "i32", "u32", "i64", "u64", "i128", "u128", "isize", "usize", "bool", "f32", "f64", "String", "char", "Option",
     ];
@@ -165,6 +172,22 @@ pub fn derive_model(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
         },
     });
 
+    let schema_fields = data.named.iter().filter_map(field_schema).collect::<Vec<_>>();
+    let schema_names = schema_fields.iter().map(|(name, _, _)| name);
+    let schema_values = schema_fields.iter().map(|(_, schema, _)| schema);
+    let schema_required = schema_fields.iter().filter(|(_, _, required)| *required).map(|(name, _, _)| name);
+
+    let field_names = data.named.iter().filter_map(|field| field.ident.as_ref().map(|ident| ident.to_string()));
+
+    let set_field_json_method = data.named.iter().filter_map(field_json_setter);
+
+    let proto_fields = data.named.iter().filter_map(proto_field).collect::<Vec<String>>();
+    let proto_message = if proto_fields.is_empty() {
+        format!("message {} {{}}\n", name)
+    } else {
+        format!("message {} {{\n{}\n}}\n", name, proto_fields.join("\n"))
+    };
+
     let expanded = quote! {
         impl ModelTrait for #name {
             fn clear_model(&self) -> Self {
@@ -198,15 +221,56 @@ pub fn derive_model(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
                 }
             }
 
+            fn set_field_json(
+                &mut self,
+                value: serde_json::Value,
+                field_name: &str,
+            ) -> std::result::Result<&Self, Box<dyn std::error::Error + Send + Sync>> {
+                match field_name {
+                    #(
+                        #set_field_json_method
+                    )*
+
+                    _ => {
+                        return Err(Box::new(ResponseError{
+                            biz_res: String::from("FIELD_MATCH_NOTHING"),
+                            message: None,
+                        }));
+                    }
+                }
+            }
+
             fn get_field_str(&self, field_name: &str) -> Option<String> {
                 match field_name {
                     #(
                         #get_field_str_method
                     )*
 
-                    _ => return None,
+                    _ => None,
                 }
-                None
+            }
+
+            fn sensitive_fields() -> &'static [&'static str] {
+                &[#(#sensitive_fields),*]
+            }
+
+            fn field_names() -> &'static [&'static str] {
+                &[#(#field_names),*]
+            }
+
+            fn proto_message() -> &'static str {
+                #proto_message
+            }
+
+            fn json_schema() -> serde_json::Value {
+                let properties = serde_json::Map::<String, serde_json::Value>::from_iter(vec![
+                    #((String::from(#schema_names), #schema_values),)*
+                ]);
+                serde_json::json!({
+                    "type": "object",
+                    "properties": properties,
+                    "required": [#(#schema_required),*],
+                })
             }
         }
     };
@@ -234,6 +298,278 @@ fn inner_for_option(ty: &Type) -> Option<Type> {
     }
 }
 
+fn generic_types(segment: &syn::PathSegment) -> Vec<&Type> {
+    match &segment.arguments {
+        syn::PathArguments::AngleBracketed(generic) => generic
+            .args
+            .iter()
+            .filter_map(|arg| match arg {
+                syn::GenericArgument::Type(ty) => Some(ty),
+                _ => None,
+            })
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// 依据字段类型生成 JSON Schema, 无法识别的类型不做约束
+fn type_schema(ty: &Type) -> proc_macro2::TokenStream {
+    let Type::Path(syn::TypePath { path, .. }) = ty else {
+        return quote! { serde_json::json!({}) };
+    };
+    let Some(segment) = path.segments.last() else {
+        return quote! { serde_json::json!({}) };
+    };
+    let generics = generic_types(segment);
+
+    match segment.ident.to_string().as_str() {
+        "i8" | "u8" | "i16" | "u16" | "i32" | "u32" => quote! { serde_json::json!({"type": "integer", "format": "int32"}) },
+        "i64" | "u64" | "i128" | "u128" | "isize" | "usize" => quote! { serde_json::json!({"type": "integer", "format": "int64"}) },
+        "f32" => quote! { serde_json::json!({"type": "number", "format": "float"}) },
+        "f64" => quote! { serde_json::json!({"type": "number", "format": "double"}) },
+        "bool" => quote! { serde_json::json!({"type": "boolean"}) },
+        "String" | "str" | "char" => quote! { serde_json::json!({"type": "string"}) },
+        "Option" | "Box" if generics.len() == 1 => type_schema(generics[0]),
+        "Vec" | "HashSet" | "BTreeSet" if generics.len() == 1 => {
+            let items = type_schema(generics[0]);
+            quote! { serde_json::json!({"type": "array", "items": #items}) }
+        }
+        "HashMap" | "BTreeMap" if generics.len() == 2 => {
+            let values = type_schema(generics[1]);
+            quote! { serde_json::json!({"type": "object", "additionalProperties": #values}) }
+        }
+        _ => quote! { serde_json::json!({}) },
+    }
+}
+
+/// 按字段上的 serde 属性从 json 值反序列化单个字段, skip 的字段不生成
+fn field_json_setter(field: &syn::Field) -> Option<proc_macro2::TokenStream> {
+    let ident = field.ident.as_ref()?;
+    let ty = &field.ty;
+    let mut skip = false;
+    let mut with = None::<syn::Path>;
+    let mut deserialize_with = None::<syn::Path>;
+
+    for attr in field.attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+        let _ = attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("skip") || meta.path.is_ident("skip_deserializing") {
+                skip = true;
+            } else if meta.path.is_ident("with") {
+                with = Some(meta.value()?.parse::<syn::LitStr>()?.parse::<syn::Path>()?);
+            } else if meta.path.is_ident("deserialize_with") {
+                deserialize_with = Some(meta.value()?.parse::<syn::LitStr>()?.parse::<syn::Path>()?);
+            } else if meta.input.peek(Token![=]) {
+                meta.value()?.parse::<syn::Expr>()?;
+            }
+            Ok(())
+        });
+    }
+    if skip {
+        return None;
+    }
+
+    let parsed = match (deserialize_with, with) {
+        (Some(deserialize_with), _) => quote! { #deserialize_with(value)? },
+        (None, Some(with)) => quote! { #with::deserialize(value)? },
+        (None, None) => quote! { serde_json::from_value::<#ty>(value)? },
+    };
+    Some(quote! {
+        stringify!(#ident) => {
+            self.#ident = #parsed;
+            Ok(self)
+        }
+    })
+}
+
+/// 单个字段的 schema: 处理 serde 的 rename/with/default 及 validator 的 length/range/email/url
+fn field_schema(field: &syn::Field) -> Option<(String, proc_macro2::TokenStream, bool)> {
+    let ident = field.ident.as_ref()?;
+    let mut name = ident.to_string();
+    let mut stringify_num = false;
+    let mut has_default = false;
+    let mut constraints = Vec::<proc_macro2::TokenStream>::new();
+
+    let is_vec = match &field.ty {
+        Type::Path(syn::TypePath { path, .. }) => path.segments.last().map(|s| s.ident == "Vec").unwrap_or(false),
+        _ => false,
+    };
+
+    for attr in &field.attrs {
+        if attr.path().is_ident("serde") {
+            let _ = attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    name = meta.value()?.parse::<syn::LitStr>()?.value();
+                } else if meta.path.is_ident("with") {
+                    stringify_num = meta.value()?.parse::<syn::LitStr>()?.value().contains("stringify_on_num");
+                } else if meta.path.is_ident("default") {
+                    has_default = true;
+                    if meta.input.peek(Token![=]) {
+                        meta.value()?.parse::<syn::Expr>()?;
+                    }
+                } else if meta.input.peek(Token![=]) {
+                    meta.value()?.parse::<syn::Expr>()?;
+                }
+                Ok(())
+            });
+        }
+
+        if attr.path().is_ident("validate") {
+            let _ = attr.parse_nested_meta(|meta| {
+                let rule = meta.path.get_ident().map(|i| i.to_string()).unwrap_or_default();
+                match rule.as_str() {
+                    "email" => constraints.push(quote! { schema["format"] = serde_json::json!("email"); }),
+                    "url" => constraints.push(quote! { schema["format"] = serde_json::json!("uri"); }),
+                    "length" | "range" => {
+                        meta.parse_nested_meta(|bound| {
+                            let bound_name = bound.path.get_ident().map(|i| i.to_string()).unwrap_or_default();
+                            let key = match (rule.as_str(), bound_name.as_str(), is_vec) {
+                                ("length", "min", false) => "minLength",
+                                ("length", "max", false) => "maxLength",
+                                ("length", "min", true) => "minItems",
+                                ("length", "max", true) => "maxItems",
+                                ("length", "equal", false) => "length",
+                                ("range", "min", _) => "minimum",
+                                ("range", "max", _) => "maximum",
+                                _ => "",
+                            };
+                            let value = bound.value()?.parse::<syn::Expr>()?;
+                            if let syn::Expr::Lit(syn::ExprLit { lit, .. }) = value {
+                                match key {
+                                    "" => {}
+                                    "length" => constraints.push(quote! {
+                                        schema["minLength"] = serde_json::json!(#lit);
+                                        schema["maxLength"] = serde_json::json!(#lit);
+                                    }),
+                                    key => constraints.push(quote! { schema[#key] = serde_json::json!(#lit); }),
+                                }
+                            }
+                            Ok(())
+                        })?;
+                    }
+                    _ => {
+                        if meta.input.peek(Token![=]) {
+                            meta.value()?.parse::<syn::Expr>()?;
+                        } else if meta.input.peek(syn::token::Paren) {
+                            let content;
+                            syn::parenthesized!(content in meta.input);
+                            content.parse::<proc_macro2::TokenStream>()?;
+                        }
+                    }
+                }
+                Ok(())
+            });
+        }
+    }
+
+    // stringify_on_num 将数字序列化为字符串
+    let schema = if stringify_num {
+        quote! { serde_json::json!({"type": "string", "pattern": "^-?\\d+$"}) }
+    } else {
+        type_schema(&field.ty)
+    };
+    let required = inner_for_option(&field.ty).is_none() && !has_default;
+
+    if constraints.is_empty() {
+        return Some((name, schema, required));
+    }
+
+    Some((
+        name,
+        quote! {
+            {
+                let mut schema = #schema;
+                #(#constraints)*
+                schema
+            }
+        },
+        required,
+    ))
+}
+
+fn screaming_snake_case(name: &str) -> String {
+    let mut snake = String::new();
+    let chars = name.chars().collect::<Vec<char>>();
+    for (i, c) in chars.iter().enumerate() {
+        if c.is_uppercase() && i > 0 && (chars[i - 1].is_lowercase() || chars.get(i + 1).map(|n| n.is_lowercase()).unwrap_or(false)) && chars[i - 1] != '_' {
+            snake.push('_');
+        }
+        snake.push(c.to_ascii_uppercase());
+    }
+    snake
+}
+
+/// Rust 类型对应的 proto message 名, google.protobuf 的类型加上包名
+fn proto_message_name(ty: &Type) -> Option<String> {
+    let Type::Path(syn::TypePath { path, .. }) = ty else {
+        return None;
+    };
+    let segment = path.segments.last()?;
+    let name = segment.ident.to_string();
+    match name.as_str() {
+        "Option" | "Vec" | "Box" => proto_message_name(generic_types(segment).first()?),
+        "HashMap" | "BTreeMap" => proto_message_name(generic_types(segment).get(1)?),
+        "Any" | "Timestamp" | "Duration" | "Struct" | "Value" | "ListValue" | "Empty" | "FieldMask" => Some(format!("google.protobuf.{}", name)),
+        _ => Some(name),
+    }
+}
+
+/// 依据 #[prost(...)] 生成 proto 字段定义, oneof 等无法表达的字段返回 None
+fn proto_field(field: &syn::Field) -> Option<String> {
+    let ident = field.ident.as_ref()?.to_string();
+    let name = ident.trim_start_matches("r#");
+    let attr = field.attrs.iter().find(|attr| attr.path().is_ident("prost"))?;
+
+    let mut proto_type = None::<String>;
+    let mut label = "";
+    let mut tag = None::<String>;
+    let mut oneof = false;
+
+    attr.parse_nested_meta(|meta| {
+        let key = meta.path.get_ident().map(|i| i.to_string()).unwrap_or_default();
+        let value = if meta.input.peek(Token![=]) {
+            Some(meta.value()?.parse::<syn::LitStr>()?.value())
+        } else {
+            None
+        };
+        match (key.as_str(), value) {
+            ("optional", _) => label = "optional ",
+            ("repeated", _) => label = "repeated ",
+            ("tag", Some(value)) => tag = Some(value),
+            ("oneof", _) => oneof = true,
+            ("message", _) | ("group", _) => proto_type = proto_message_name(&field.ty),
+            ("enumeration", Some(value)) => proto_type = Some(value),
+            ("map", Some(value)) | ("btree_map", Some(value)) => {
+                let Some((key_type, value_type)) = value.split_once(",") else {
+                    return Ok(());
+                };
+                let value_type = value_type.trim();
+                let value_type = if value_type == "message" {
+                    proto_message_name(&field.ty).unwrap_or_default()
+                } else if let Some(enumeration) = value_type.strip_prefix("enumeration(") {
+                    enumeration.trim_end_matches(")").to_string()
+                } else {
+                    value_type.to_string()
+                };
+                proto_type = Some(format!("map<{}, {}>", key_type.trim(), value_type));
+            }
+            ("bytes", _) => proto_type = Some("bytes".to_string()),
+            ("packed", _) | ("default", _) | ("required", _) => {}
+            (scalar, _) => proto_type = Some(scalar.to_string()),
+        }
+        Ok(())
+    })
+    .ok()?;
+
+    if oneof {
+        return None;
+    }
+    // map 字段不能带 label
+    if proto_type.as_deref().map(|t| t.starts_with("map<")).unwrap_or(false) {
+        label = "";
+    }
+    Some(format!("  {}{} {} = {};", label, proto_type?, name, tag?))
+}
+
 #[proc_macro_derive(EnumGenerate)]
 pub fn enum_generate(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     let input_copy = input.clone();
@@ -591,11 +927,36 @@ mod stringify_enum_{{enum_name_str_lowercase}}_map {
         Err(_) => return input_and_compile_error(input_copy, syn::Error::new(enum_name.span(), "construct token stream error from string")),
     };
 
+    // proto3 的枚举值在 package 内唯一且首个值须为 0, 以枚举名为前缀并补充 UNSPECIFIED
+    let proto_prefix = screaming_snake_case(&enum_name_str);
+    let mut proto_values: Vec<String> = vec![];
+    if !enum_variants.iter().any(|(_, ord)| ord.base10_parse::<i32>().map(|v| v == 0).unwrap_or(false)) {
+        proto_values.push(format!("  {}_UNSPECIFIED = 0;", proto_prefix));
+    }
+    enum_variants.iter().for_each(|(name, ord)| {
+        proto_values.push(format!(
+            "  {}_{} = {};",
+            proto_prefix,
+            screaming_snake_case(&name.to_string()),
+            ord.base10_digits()
+        ));
+    });
+    let proto_enum = format!("enum {} {{\n{}\n}}\n", enum_name_str, proto_values.join("\n"));
+
+    let proto_enum_token = quote! {
+        impl #enum_name {
+            pub fn proto_enum() -> &'static str {
+                #proto_enum
+            }
+        }
+    };
+
     quote! {
         #gen_from_token
         #to_string_token
         #lit_val_to_i32_token
         #to_i32_token
+        #proto_enum_token
         #enum_option_token
         #enum_prim_token
         #enum_vec_token
@@ -757,6 +1118,12 @@ pub fn biz_result_handler(args: proc_macro::TokenStream, input: proc_macro::Toke
     biz_res_needed.push(BizResultArg::new("DATA_ERROR", 500, 27, "data error"));
     biz_res_needed.push(BizResultArg::new("AUTH_ERROR", 401, 28, "auth error"));
     biz_res_needed.push(BizResultArg::new("INTERNAL_AUTH_TAG_NOT_SET", 500, 29, "internal auth tag not set"));
+    biz_res_needed.push(BizResultArg::new("REQUEST_BODY_TOO_LARGE", 413, 30, "request body too large"));
+    biz_res_needed.push(BizResultArg::new("NOT_ACCEPTABLE", 406, 31, "not acceptable"));
+    biz_res_needed.push(BizResultArg::new("TOO_MANY_REQUESTS", 429, 32, "too many requests"));
+    biz_res_needed.push(BizResultArg::new("IDEMPOTENCY_KEY_IN_PROGRESS", 409, 33, "idempotency key in progress"));
+    biz_res_needed.push(BizResultArg::new("METHOD_NOT_ALLOWED", 405, 34, "method not allowed"));
+    biz_res_needed.push(BizResultArg::new("IDEMPOTENCY_KEY_REUSED", 422, 35, "idempotency key reused"));
 
     args.biz_results.extend(biz_res_needed);
 
@@ -790,7 +1157,9 @@ pub fn uri_handler(args: proc_macro::TokenStream, input: proc_macro::TokenStream
     };
 
     let mut tokens: proc_macro::TokenStream = format!(
-        "generate_http_dispatcher!({}, [{}]);\ngenerate_grpc_dispatcher!({}, [{}]);\n",
+        "generate_http_dispatcher!({}, [{}]);\ngenerate_grpc_dispatcher!({}, [{}]);\ngenerate_native_grpc_dispatcher!({}, [{}]);\n",
+        ast.ident.to_string(),
+        args.to_string(),
         ast.ident.to_string(),
         args.to_string(),
         ast.ident.to_string(),
@@ -990,6 +1359,11 @@ impl syn::parse::Parse for URIHandlerArgs {
                 err
             })?;
 
+            // 同一个 uri 只能有一个 handler, 重复时后者在分发中永远不会被匹配
+            if handlers.iter().any(|handler| handler.uri == uri) {
+                return Err(syn::Error::new(uri.span(), format!("duplicate uri '{}' in uri_handler", uri)));
+            }
+
             handlers.push(URIHandler { uri, fn_name })
         }
 